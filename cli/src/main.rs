@@ -1,10 +1,17 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 use clap::{Parser, Subcommand};
-use libunits_converter::unitquery::{SqlUnitQuery, UnitQuery};
-use libunits_converter::*;
+use libunits_converter::aio::{AsyncUnitConverter, AsyncUnitFactory, AsyncUnitQuery};
+use libunits_converter::unitquery::{OverlayUnitQuery, SqlUnitQuery, UnitRegistry};
+use libunits_converter::{
+    Dimension, Evaluator, InlineUnitParser, MainConverter, MainUnitFactory, Rational, UnitError,
+    Value, construct_all,
+};
+
+mod repl;
 
 #[derive(Parser, Default, Clone)]
 pub struct ConvertArgs {
@@ -20,11 +27,67 @@ pub struct DimArgs {
     pub unit2: Option<String>,
 }
 
+#[derive(Parser, Default, Clone)]
+pub struct EvalArgs {
+    /// Expression over values with units, its words joined by spaces (`3 km / 20 min to m/s`)
+    #[arg(required = true, allow_hyphen_values = true)]
+    pub expression: Vec<String>,
+}
+
+#[derive(Parser, Default, Clone)]
+pub struct ListArgs {
+    /// Show the aliases of each unit after its name
+    #[clap(long, short, action=clap::ArgAction::SetTrue)]
+    pub aliases: bool,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum UnitAction {
+    /// Define NAME as FACTOR times the unit expression EXPR (`unit add knot 1852 m/h`)
+    Add {
+        name: String,
+        factor: f64,
+        expr: String,
+    },
+    /// Remove a user defined unit
+    Remove { name: String },
+    /// Define a dimension from its exponents of mass, duration, length, temperature, current,
+    /// amount and luminosity (`unit dimension speed 0 -1 1 0 0 0 0`)
+    Dimension {
+        name: String,
+        #[arg(
+            num_args = 7,
+            required = true,
+            allow_hyphen_values = true,
+            value_name = "EXPONENT"
+        )]
+        exponents: Vec<Rational>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum DbAction {
+    /// Check the catalogue and the user units: dimensions, unique names, factors and definitions
+    Check,
+}
+
 #[derive(Subcommand, Clone)]
 pub enum Mode {
     Convert(ConvertArgs),
     Dim(DimArgs),
-    List,
+    /// Evaluate an expression: + - * / ^, parentheses, `to`/`in` a unit and `name = value`
+    /// variables between statements separated by `;`
+    Eval(EvalArgs),
+    /// Interactive session: expressions as for eval, variables kept between lines, `ans` and
+    /// tab completion of unit names
+    Repl,
+    List(ListArgs),
+    /// Manage the user defined units, kept apart from the shipped catalogue
+    #[command(subcommand)]
+    Unit(UnitAction),
+    /// Maintain the unit databases
+    #[command(subcommand)]
+    Db(DbAction),
 }
 #[derive(Parser, Clone)]
 #[command(
@@ -52,10 +115,86 @@ By {author}
 pub struct GenArgs {
     #[clap(long, short, action=clap::ArgAction::SetTrue)]
     verbose: bool,
+    /// Unit database, defaults to $RUNIT_DB then runit-converter/sqlite.db in the XDG data directories
+    #[clap(long, global = true)]
+    db: Option<PathBuf>,
+    /// User units database, defaults to $RUNIT_USER_DB then runit-converter/user.db in $XDG_DATA_HOME
+    #[clap(long, global = true)]
+    user_db: Option<PathBuf>,
     #[clap(subcommand)]
     pub mode: Mode,
 }
 
+async fn open_db(
+    db: Option<&Path>,
+    user_db: Option<&Path>,
+) -> Result<
+    (
+        InlineUnitParser,
+        MainUnitFactory<OverlayUnitQuery>,
+        MainConverter<OverlayUnitQuery>,
+    ),
+    ExitCode,
+> {
+    construct_all(db, user_db).await.map_err(|e| {
+        eprintln!("{}", e);
+        ExitCode::FAILURE
+    })
+}
+
+async fn edit_units(db: Option<&Path>, user_db: Option<&Path>, action: UnitAction) -> ExitCode {
+    let result = async {
+        let base = SqlUnitQuery::open(SqlUnitQuery::locate(db)?).await?;
+        let user_db = OverlayUnitQuery::locate(user_db)?;
+        let registry = match action {
+            UnitAction::Remove { .. } => OverlayUnitQuery::open(base, user_db).await?,
+            _ => OverlayUnitQuery::create(base, user_db).await?,
+        };
+        match action {
+            UnitAction::Add { name, factor, expr } => {
+                registry.define_unit(&name, &expr, factor).await
+            }
+            UnitAction::Remove { name } => registry.remove_unit(&name).await,
+            UnitAction::Dimension { name, exponents } => {
+                let mut dimension = Dimension::default();
+                dimension.0.copy_from_slice(&exponents);
+                registry.define_dimension(&name, dimension).await
+            }
+        }
+    };
+    match result.await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err::<(), UnitError>(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn check_db(db: Option<&Path>, user_db: Option<&Path>) -> ExitCode {
+    let diagnostics = match OverlayUnitQuery::new(db, user_db).await {
+        Ok(c) => c.check().await,
+        Err(e) => Err(e),
+    };
+    match diagnostics {
+        Ok(diagnostics) if diagnostics.is_empty() => {
+            println!("No problem found");
+            ExitCode::SUCCESS
+        }
+        Ok(diagnostics) => {
+            for diagnostic in &diagnostics {
+                println!("{}", diagnostic);
+            }
+            eprintln!("{} problem(s) found", diagnostics.len());
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> ExitCode {
     let args = GenArgs::parse();
@@ -67,15 +206,23 @@ async fn main() -> ExitCode {
 
             unit2,
         }) => {
-            let (parser, factory, converter) = construct_all().await;
+            let (parser, factory, converter) =
+                match open_db(args.db.as_deref(), args.user_db.as_deref()).await {
+                    Ok(all) => all,
+                    Err(code) => return code,
+                };
 
-            let runit1 = factory.parse_fill(&parser, &unit1);
-            let runit2 = factory.parse_fill(&parser, &unit2);
+            let runit1 = factory.parse_fill(&parser, &unit1).await;
+            let runit2 = factory.parse_fill(&parser, &unit2).await;
 
             if let (Ok(unit1), Ok(unit2)) = (runit1.as_ref(), runit2.as_ref()) {
                 let value1 = Value::from_value(unit1.clone(), value);
 
-                match converter.convert(&value1, unit2) {
+                match converter.convert(&value1, unit2).await {
+                    Ok(val) if args.verbose => {
+                        println!("{} = {}", value1, val);
+                        return ExitCode::SUCCESS;
+                    }
                     Ok(val) => {
                         println!("{}", val.value);
                         return ExitCode::SUCCESS;
@@ -89,36 +236,43 @@ async fn main() -> ExitCode {
                 }
             } else if args.verbose {
                 if let Err(e) = runit1 {
-                    println!("Unit1 : {}", e)
+                    println!("Unit1 : {}", e.render(&unit1))
                 }
                 if let Err(e) = runit2 {
-                    println!("Unit2 : {}", e)
+                    println!("Unit2 : {}", e.render(&unit2))
                 }
             }
             return ExitCode::FAILURE;
         }
         Mode::Dim(DimArgs { unit1, unit2 }) => {
-            let (parser, factory, converter) = construct_all().await;
-            let runit1 = factory.parse_fill(&parser, &unit1);
+            let (parser, factory, converter) =
+                match open_db(args.db.as_deref(), args.user_db.as_deref()).await {
+                    Ok(all) => all,
+                    Err(code) => return code,
+                };
+            let runit1 = factory.parse_fill(&parser, &unit1).await;
 
             if let Err(e) = runit1 {
-                println!("Unit1 : {}", e);
+                println!("Unit1 : {}", e.render(&unit1));
                 return ExitCode::FAILURE;
             }
 
             if let Some(unit2) = unit2 {
-                let runit2 = factory.parse_fill(&parser, &unit2);
+                let runit2 = factory.parse_fill(&parser, &unit2).await;
                 if let Err(e) = runit2 {
-                    println!("Unit2 : {}", e);
+                    println!("Unit2 : {}", e.render(&unit2));
                     return ExitCode::FAILURE;
                 }
-                let flag = if converter
+                let flag = match converter
                     .are_same_dimension(&runit1.unwrap(), &runit2.unwrap())
-                    .0
+                    .await
                 {
-                    0
-                } else {
-                    1
+                    Ok((true, _, _)) => 0,
+                    Ok((false, _, _)) => 1,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return ExitCode::FAILURE;
+                    }
                 };
 
                 println!("{}", flag);
@@ -127,23 +281,111 @@ async fn main() -> ExitCode {
                 return flag.into();
             } else {
                 //Safe to unwrap before tested before
-                let dim = converter.get_dimension(&runit1.unwrap());
-                if (args.verbose) {
+                let unit = runit1.unwrap();
+                let dim = match converter.get_dimension(&unit).await {
+                    Ok(dim) => dim,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return ExitCode::FAILURE;
+                    }
+                };
+                if args.verbose {
+                    match converter.simplify(&unit).await {
+                        Ok(simplified) if simplified != unit => {
+                            println!("{} = {}", unit, simplified)
+                        }
+                        Ok(_) => {}
+                        Err(e) => eprintln!("{}", e),
+                    }
                     println!("{}", dim.pretty_print());
                 } else {
                     println!("{}", dim);
                 }
+                let query = converter.query();
+                let named = async {
+                    let quantities = query.find_dimension_by_vector(&dim).await?;
+                    Ok::<_, UnitError>((quantities, query.find_units_by_vector(&dim).await?))
+                };
+                match named.await {
+                    Ok((quantities, units)) => {
+                        if !quantities.is_empty() {
+                            println!("Quantity: {}", quantities.join(", "));
+                        }
+                        if !units.is_empty() {
+                            println!("Units: {}", units.join(", "));
+                        }
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
 
                 return ExitCode::SUCCESS;
             }
         }
-        Mode::List => {
-            let c = std::rc::Rc::new(SqlUnitQuery::new().await.unwrap());
-            let names = c.get_unit_list().unwrap();
+        Mode::Eval(EvalArgs { expression }) => {
+            let (parser, factory, converter) =
+                match open_db(args.db.as_deref(), args.user_db.as_deref()).await {
+                    Ok(all) => all,
+                    Err(code) => return code,
+                };
+            let input = expression.join(" ");
+            match Evaluator::new()
+                .eval(&parser, &factory, &converter, &input)
+                .await
+            {
+                Ok(value) => {
+                    println!("{}", value);
+                    return ExitCode::SUCCESS;
+                }
+                Err(e) => {
+                    eprintln!("{}", e.render(&input));
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        Mode::Repl => {
+            let (parser, factory, converter) =
+                match open_db(args.db.as_deref(), args.user_db.as_deref()).await {
+                    Ok(all) => all,
+                    Err(code) => return code,
+                };
+            return repl::run(parser, factory, converter, args.user_db.as_deref()).await;
+        }
+        Mode::List(ListArgs { aliases }) => {
+            let names =
+                match OverlayUnitQuery::new(args.db.as_deref(), args.user_db.as_deref()).await {
+                    Ok(c) if aliases => match c.get_unit_list().await {
+                        Ok(names) => c.get_aliases().await.map(|aliases| (names, aliases)),
+                        Err(e) => Err(e),
+                    },
+                    Ok(c) => c.get_unit_list().await.map(|names| (names, Vec::new())),
+                    Err(e) => Err(e),
+                };
+            let (names, aliases) = match names {
+                Ok(names) => names,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return ExitCode::FAILURE;
+                }
+            };
             names.iter().for_each(|unit| {
-                println!("{}", unit);
+                let unit_aliases: Vec<&str> = aliases
+                    .iter()
+                    .filter(|(_, of)| of == unit)
+                    .map(|(alias, _)| alias.as_str())
+                    .collect();
+                if unit_aliases.is_empty() {
+                    println!("{}", unit);
+                } else {
+                    println!("{} ({})", unit, unit_aliases.join(", "));
+                }
             });
         }
+        Mode::Unit(action) => {
+            return edit_units(args.db.as_deref(), args.user_db.as_deref(), action).await;
+        }
+        Mode::Db(DbAction::Check) => {
+            return check_db(args.db.as_deref(), args.user_db.as_deref()).await;
+        }
     }
     return ExitCode::SUCCESS;
 }
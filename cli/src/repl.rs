@@ -0,0 +1,248 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Interactive session over one opened catalogue: expressions as read by `eval`, variables kept
+//! from a line to the next and the last result bound to `ans`.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use libunits_converter::aio::AsyncUnitQuery;
+use libunits_converter::unitquery::OverlayUnitQuery;
+use libunits_converter::{
+    Evaluator, InlineUnitParser, MainConverter, MainUnitFactory, UnitError, UnitParser,
+};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+/// Variable holding the result of the last evaluated line.
+const ANSWER: &str = "ans";
+
+/// History file, next to the user database.
+const HISTORY_FILE: &str = "history";
+
+const COMMANDS: [&str; 5] = [":help", ":vars", ":delimiter", ":exp", ":quit"];
+
+const HELP: &str = "\
+Expressions: + - * / ^, parentheses, `to`/`in` a unit, `name = value`, `;` between statements
+`ans` holds the last result
+:vars               list the variables
+:delimiter [SYMBOL] show or set the symbol multiplying units (`*`)
+:exp [SYMBOL]       show or set the exponent symbol (`^`)
+:quit               leave, as Ctrl-D does";
+
+/// Characters ending a unit or variable name before the cursor.
+fn is_boundary(c: char) -> bool {
+    c.is_whitespace() || "+-*/^()=;×·⋅÷−".contains(c)
+}
+
+/// Tab completion of commands, variables and unit names with their aliases.
+struct UnitHelper {
+    units: Vec<String>,
+    variables: Vec<String>,
+    /// Symbols of the unit parser, ending a name as well.
+    delimiter: String,
+    exp_symbol: String,
+}
+
+impl Completer for UnitHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        if before.starts_with(':') && !before.contains(char::is_whitespace) {
+            let commands = COMMANDS.iter().filter(|c| c.starts_with(before));
+            return Ok((0, commands.map(|c| c.to_string()).collect()));
+        }
+        let mut start = before
+            .rfind(is_boundary)
+            .map_or(0, |i| i + before[i..].chars().next().unwrap().len_utf8());
+        for symbol in [&self.delimiter, &self.exp_symbol] {
+            if let Some(i) = before[start..].rfind(symbol.as_str()) {
+                start += i + symbol.len();
+            }
+        }
+        let word = &before[start..];
+        if word.is_empty() || word.starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok((pos, Vec::new()));
+        }
+        let mut candidates: Vec<String> = self
+            .variables
+            .iter()
+            .chain(&self.units)
+            .filter(|name| name.starts_with(word))
+            .cloned()
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for UnitHelper {
+    type Hint = String;
+}
+
+impl Highlighter for UnitHelper {}
+
+impl Validator for UnitHelper {}
+
+impl Helper for UnitHelper {}
+
+/// History location, `None` when there is no data directory to keep it in.
+fn history_path(user_db: Option<&Path>) -> Option<PathBuf> {
+    OverlayUnitQuery::locate(user_db)
+        .ok()
+        .map(|path| path.with_file_name(HISTORY_FILE))
+}
+
+/// Reads lines until Ctrl-D or `:quit`. Errors are printed and the session goes on.
+pub async fn run(
+    mut parser: InlineUnitParser,
+    factory: MainUnitFactory<OverlayUnitQuery>,
+    converter: MainConverter<OverlayUnitQuery>,
+    user_db: Option<&Path>,
+) -> ExitCode {
+    let query = converter.query();
+    let units = async {
+        let mut units = query.get_unit_list().await?;
+        units.extend(
+            query
+                .get_aliases()
+                .await?
+                .into_iter()
+                .map(|(alias, _)| alias),
+        );
+        Ok::<_, UnitError>(units)
+    };
+    let units = match units.await {
+        Ok(units) => units,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut editor: Editor<UnitHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    editor.set_helper(Some(UnitHelper {
+        units,
+        variables: Vec::new(),
+        delimiter: parser.get_delimiter().clone(),
+        exp_symbol: parser.get_exp_symbol().clone(),
+    }));
+    let history = history_path(user_db);
+    if let Some(path) = &history {
+        // Missing on the first session
+        let _ = editor.load_history(path);
+    }
+
+    let mut evaluator = Evaluator::new();
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+
+        if let Some(command) = line.strip_prefix(':') {
+            let mut words = command.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("quit" | "q"), _) => break,
+                (Some("help" | "h"), _) => println!("{}", HELP),
+                (Some("vars"), _) => {
+                    let mut variables: Vec<_> = evaluator.variables().iter().collect();
+                    variables.sort_by_key(|(name, _)| *name);
+                    for (name, value) in variables {
+                        println!("{} = {}", name, value);
+                    }
+                }
+                (Some("delimiter"), None) => println!("{}", parser.get_delimiter()),
+                (Some("exp"), None) => println!("{}", parser.get_exp_symbol()),
+                (Some("delimiter"), Some(symbol)) => match parser.set_delimiter(symbol) {
+                    true => editor.helper_mut().unwrap().delimiter = symbol.to_owned(),
+                    false => eprintln!("Unsafe delimiter: {}", symbol),
+                },
+                (Some("exp"), Some(symbol)) => match parser.set_exp_symbol(symbol) {
+                    true => editor.helper_mut().unwrap().exp_symbol = symbol.to_owned(),
+                    false => eprintln!("Unsafe exponent symbol: {}", symbol),
+                },
+                _ => eprintln!("Unknown command: {}, :help lists them", line),
+            }
+            continue;
+        }
+
+        match evaluator.eval(&parser, &factory, &converter, line).await {
+            Ok(value) => {
+                println!("{}", value);
+                evaluator.set_variable(ANSWER, value);
+            }
+            Err(e) => eprintln!("{}", e.render(line)),
+        }
+        // Statements before a failing one may have bound variables as well
+        editor.helper_mut().unwrap().variables = evaluator.variables().keys().cloned().collect();
+    }
+
+    if let Some(path) = &history
+        && path.parent().is_some_and(|dir| dir.is_dir())
+    {
+        let _ = editor.save_history(path);
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_complete() {
+        let helper = UnitHelper {
+            units: ["m", "min", "mol", "metre", "kg"]
+                .map(String::from)
+                .to_vec(),
+            variables: vec!["mass".to_owned()],
+            delimiter: ".".to_owned(),
+            exp_symbol: "**".to_owned(),
+        };
+        let history = DefaultHistory::new();
+        let complete = |line: &str| {
+            let context = Context::new(&history);
+            helper.complete(line, line.len(), &context).unwrap()
+        };
+
+        assert!(complete(":e") == (0, vec![":exp".to_owned()]));
+        assert!(complete("3 mi") == (2, vec!["min".to_owned()]));
+        let names = ["m", "mass", "metre", "min", "mol"]
+            .map(String::from)
+            .to_vec();
+        assert!(complete("(kg.m") == (4, names));
+        assert!(complete("m**2/mo") == (5, vec!["mol".to_owned()]));
+        assert!(complete("2 km**m").0 == 6);
+        // Nothing after a number or a space
+        assert!(complete("3").1.is_empty());
+        assert!(complete("3 ").1.is_empty());
+    }
+}
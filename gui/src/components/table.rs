@@ -1,8 +1,9 @@
 // SPDX-License-Identifer: GPL-3.0-or-later
 use dioxus::prelude::*;
-use libunits_converter::{UnitConverter, UnitFactory, UnitParser, Value};
+use libunits_converter::aio::{AsyncUnitConverter, AsyncUnitFactory};
+use libunits_converter::{UnitError, Value};
 
-use crate::MainContext;
+use crate::SharedContext;
 
 #[derive(Clone, PartialEq)]
 struct UnitState {
@@ -10,8 +11,32 @@ struct UnitState {
     unit1: Signal<String>,
     unit2: Signal<String>,
     res: Signal<f64>,
+    error: Signal<Option<RowError>>,
 }
 
+/// Error of a row, with the input split around the offending part when it is known.
+#[derive(Clone, PartialEq)]
+struct RowError {
+    message: String,
+    before: String,
+    token: String,
+    after: String,
+}
+
+impl RowError {
+    fn new(input: &str, error: &UnitError) -> Self {
+        let (before, token, after) = error.highlight(input).unwrap_or(("", "", ""));
+        RowError {
+            message: error.to_string(),
+            before: before.to_owned(),
+            token: token.to_owned(),
+            after: after.to_owned(),
+        }
+    }
+}
+
+type RowData = (f64, String, String, Signal<f64>, Signal<Option<RowError>>);
+
 impl UnitState {
     fn new() -> Self {
         UnitState {
@@ -19,14 +44,16 @@ impl UnitState {
             unit1: use_signal(String::new),
             unit2: use_signal(String::new),
             res: use_signal(|| 0.),
+            error: use_signal(|| None),
         }
     }
-    fn get_data(&self) -> (f64, String, String, Signal<f64>) {
+    fn get_data(&self) -> RowData {
         (
             *self.val.read(),
             self.unit1.read().clone(),
             self.unit2.read().clone(),
             self.res,
+            self.error,
         )
     }
 }
@@ -58,10 +85,29 @@ fn ValUnitInput(unit: Signal<f64>) -> Element {
 }
 
 #[component]
-fn RowUnitTable(
-    on_remove: EventHandler,
-    on_ok: EventHandler<(f64, String, String, Signal<f64>)>,
-) -> Element {
+fn ErrorCell(error: Signal<Option<RowError>>) -> Element {
+    let Some(e) = error.read().clone() else {
+        return rsx! { td {} };
+    };
+    let located = (!e.token.is_empty()).then(|| {
+        rsx! {
+            div { class: "error-input",
+                "{e.before}"
+                u { "{e.token}" }
+                "{e.after}"
+            }
+        }
+    });
+    rsx! {
+        td { class: "error",
+            "{e.message}"
+            {located}
+        }
+    }
+}
+
+#[component]
+fn RowUnitTable(on_remove: EventHandler, on_ok: EventHandler<RowData>) -> Element {
     let state = UnitState::new();
     let s = state.clone();
 
@@ -75,6 +121,7 @@ fn RowUnitTable(
             td { UnitInput { unit: state.unit1 } }
             td { UnitInput { unit: state.unit2 } }
             td { "{state.res.read()}" }
+            ErrorCell { error: state.error }
             td {
                 button { onclick: move |_| on_remove.call(()), "Delete" }
             }
@@ -91,7 +138,7 @@ fn RowUnitTable(
 
 #[component]
 pub fn UnitTable() -> Element {
-    let ctx = use_context::<Signal<MainContext>>();
+    let ctx = use_context::<SharedContext>();
     let mut rows = use_signal(Vec::<UnitState>::new);
     let add_row = move |_| {
         rows.with_mut(|v| v.push(UnitState::new()));
@@ -104,27 +151,37 @@ pub fn UnitTable() -> Element {
         });
     };
 
-    let handle_ok = move |(val, unit1, unit2, mut res): (f64, String, String, Signal<f64>)| {
-        let parser = &ctx.read().0;
-        let factory = &ctx.read().1;
-        let converter = &ctx.read().2;
+    let handle_ok = move |(val, unit1, unit2, mut res, mut error): RowData| {
+        // Cloned out of the signal, so that no borrow of it is held across the awaits
+        let Some(ctx) = ctx.read().clone() else {
+            return;
+        };
+        spawn(async move {
+            let (parser, factory, converter) = (&ctx.0, &ctx.1, &ctx.2);
 
-        let runit1 = factory.parse_fill(parser, &unit1);
-        let runit2 = factory.parse_fill(parser, &unit2);
+            let runit1 = factory.parse_fill(parser, &unit1).await;
+            let runit2 = factory.parse_fill(parser, &unit2).await;
 
-        if let (Ok(unit1), Ok(unit2)) = (runit1.as_ref(), runit2.as_ref()) {
-            let value1 = Value::from_value(unit1.clone(), val);
+            match (runit1.as_ref(), runit2.as_ref()) {
+                (Ok(unit1), Ok(unit2)) => {
+                    let value1 = Value::from_value(unit1.clone(), val);
 
-            match converter.convert(&value1, &unit2) {
-                Ok(val) => {
-                    *res.write() = val.value;
-                    println!("{}", val.value);
-                }
-                Err(e) => {
-                    eprintln!("{}", e);
+                    match converter.convert(&value1, unit2).await {
+                        Ok(val) => {
+                            *res.write() = val.value;
+                            error.set(None);
+                            println!("{}", val.value);
+                        }
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            error.set(Some(RowError::new("", &e)));
+                        }
+                    }
                 }
+                (Err(e), _) => error.set(Some(RowError::new(&unit1, e))),
+                (_, Err(e)) => error.set(Some(RowError::new(&unit2, e))),
             }
-        }
+        });
     };
 
     rsx! {
@@ -135,6 +192,7 @@ pub fn UnitTable() -> Element {
                 th { "Unit 1" }
                 th { "Unit 2" }
                 th { "Result" }
+                th { "Error" }
                 th { "Action" }
                 th { "Ok" }
             }
@@ -28,31 +28,42 @@ fn Wrapper() -> Element {
         footer { Footer{} }
     }
 }
-use futures::executor::block_on;
+use std::sync::Arc;
+
 use libunits_converter::{
-    InlineUnitParser, MainConverter, MainUnitFactory, construct_all, unitquery::SqlUnitQuery,
+    InlineUnitParser, MainConverter, MainUnitFactory, UnitError, construct_all,
+    unitquery::OverlayUnitQuery,
 };
 
 struct MainContext(
     InlineUnitParser,
-    MainUnitFactory<SqlUnitQuery>,
-    MainConverter<SqlUnitQuery>,
+    MainUnitFactory<OverlayUnitQuery>,
+    MainConverter<OverlayUnitQuery>,
 );
 
-impl MainContext {
-    pub fn new() -> Self {
-        let (parser, factory, converter) = block_on(construct_all());
-        Self(parser, factory, converter)
-    }
-}
+/// Context shared by the components, `None` until the unit database is open.
+type SharedContext = Signal<Option<Arc<MainContext>>>;
 
 #[component]
 fn App() -> Element {
-    let global_cxt = use_context_provider(|| Signal::new(MainContext::new()));
+    let mut global_cxt: SharedContext = use_context_provider(|| Signal::new(None));
+    // Opened without blocking the first render, a failure is shown in the window instead of
+    // aborting
+    let opened = use_resource(move || async move {
+        let (parser, factory, converter) = construct_all(None, None).await?;
+        global_cxt.set(Some(Arc::new(MainContext(parser, factory, converter))));
+        Ok::<_, UnitError>(())
+    });
+
+    let content = match &*opened.read() {
+        None => rsx! { p { "Opening the unit database..." } },
+        Some(Err(e)) => rsx! { p { class: "error", "Unable to open the unit database: {e}" } },
+        Some(Ok(())) => rsx! { Router::<Route> {} },
+    };
 
     rsx! {
     document::Link { rel: "icon", href: FAVICON }
     document::Link { rel: "stylesheet", href: MAIN_CSS }
 
-    Router::<Route> {} }
+    {content} }
 }
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Converts the same column of values with and without [`CachedUnitQuery`].
+//!
+//! `cargo bench -p libunits_converter --bench cache [rows]`, the database is found the same way
+//! as the CLI does (`RUNIT_DB`, XDG data directories).
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use libunits_converter::aio::AsyncUnitQuery;
+use libunits_converter::unitquery::{CachedUnitQuery, SqlUnitQuery};
+use libunits_converter::{
+    InlineUnitParser, MainConverter, MainUnitFactory, UnitConverter, UnitFactory, Value,
+};
+
+fn run<Q: AsyncUnitQuery>(query: Q, rows: usize) -> Duration {
+    let query = Arc::new(query);
+    let parser = InlineUnitParser::default();
+    let factory = MainUnitFactory::new(query.clone());
+    let converter = MainConverter::new(query);
+
+    let start = Instant::now();
+    let from = factory.parse_fill(&parser, "km/h").unwrap();
+    let to = factory.parse_fill(&parser, "m/s").unwrap();
+    for i in 0..rows {
+        let value = Value::from_value(from.clone(), i as f64);
+        std::hint::black_box(converter.convert(&value, &to).unwrap());
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let rows = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(10_000);
+    let open = || futures::executor::block_on(SqlUnitQuery::new()).expect("unit database");
+
+    let direct = run(open(), rows);
+    let cached = run(CachedUnitQuery::new(open()), rows);
+    println!("{} conversions km/h -> m/s", rows);
+    println!("  {:<30} {:>10.2?}", "SqlUnitQuery", direct);
+    println!(
+        "  {:<30} {:>10.2?}",
+        "CachedUnitQuery<SqlUnitQuery>", cached
+    );
+    println!(
+        "  {:<30} {:>9.1}x",
+        "speedup",
+        direct.as_secs_f64() / cached.as_secs_f64()
+    );
+}
@@ -0,0 +1,320 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Async counterparts of [`UnitFactory`](crate::UnitFactory) and
+//! [`UnitConverter`](crate::UnitConverter), for callers already running an executor (tokio,
+//! dioxus) where the blocking API would stall the runtime.
+//!
+//! Methods share their name with the blocking traits, import only the ones matching the context.
+
+use crate::{
+    Dimension, ElementUnit, MainConverter, MainUnitFactory, Rational, Unit, UnitError, UnitParser,
+    Value, definition,
+};
+
+pub use crate::definition::check_definitions_async;
+pub use crate::unitquery::AsyncUnitQuery;
+
+pub trait AsyncUnitFactory {
+    fn construct_unit(
+        &self,
+        name: &str,
+        exp: impl Into<Rational>,
+    ) -> impl Future<Output = Result<ElementUnit, UnitError>>;
+    fn fill(&self, unit: &mut ElementUnit) -> impl Future<Output = Result<(), UnitError>>;
+    fn parse_fill<T: UnitParser>(
+        &self,
+        parser: &T,
+        text: &str,
+    ) -> impl Future<Output = Result<Unit, UnitError>>;
+}
+
+pub trait AsyncUnitConverter {
+    fn is_valid_unit(&self, unit: &Unit) -> impl Future<Output = Result<bool, UnitError>>;
+    fn are_same_dimension(
+        &self,
+        unit1: &Unit,
+        unit2: &Unit,
+    ) -> impl Future<Output = Result<(bool, Dimension, Dimension), UnitError>>;
+    fn get_dimension(&self, unit: &Unit) -> impl Future<Output = Result<Dimension, UnitError>>;
+    /// [`AsyncUnitConverter::get_dimension`], naming on the way the dimension of each partial.
+    fn get_dimension_mut(
+        &self,
+        unit: &mut Unit,
+    ) -> impl Future<Output = Result<Dimension, UnitError>>;
+    fn convert(&self, value: &Value, unit: &Unit)
+    -> impl Future<Output = Result<Value, UnitError>>;
+    fn get_conversion_factor(&self, unit: &Unit) -> impl Future<Output = Result<f64, UnitError>>;
+    fn get_conversion_offset(&self, unit: &Unit) -> impl Future<Output = Result<f64, UnitError>>;
+    fn simplify(&self, unit: &Unit) -> impl Future<Output = Result<Unit, UnitError>>;
+    fn simplify_value(&self, value: &Value) -> impl Future<Output = Result<Value, UnitError>>;
+    fn add(&self, lhs: &Value, rhs: &Value) -> impl Future<Output = Result<Value, UnitError>>;
+    fn sub(&self, lhs: &Value, rhs: &Value) -> impl Future<Output = Result<Value, UnitError>>;
+}
+
+impl<T: AsyncUnitQuery> AsyncUnitFactory for MainUnitFactory<T> {
+    async fn construct_unit(
+        &self,
+        name: &str,
+        exp: impl Into<Rational>,
+    ) -> Result<ElementUnit, UnitError> {
+        let mut unit = ElementUnit::new(name, exp);
+        self.fill(&mut unit).await?;
+        Ok(unit)
+    }
+
+    async fn fill(&self, unit: &mut ElementUnit) -> Result<(), UnitError> {
+        let (factor, _) = match definition::resolve_async(&*self.query, unit).await {
+            Ok(resolved) => resolved,
+            Err(e) if e.is_unknown(unit) => {
+                let suggestions = self.query.suggest(&unit.name).await.unwrap_or_default();
+                return Err(e.with_suggestions(suggestions));
+            }
+            Err(e) => return Err(e),
+        };
+        match self.query.get_dimension_name(unit).await {
+            Ok(name) => unit.set_dim(&name),
+            // Units known only by their definition have no dimension name
+            Err(UnitError::NoStoredDimension { .. }) => {}
+            Err(e) => return Err(e),
+        }
+        unit.set_factor(factor);
+        unit.set_offset(self.query.get_conversion_offset(unit).await?);
+        Ok(())
+    }
+
+    async fn parse_fill<G: UnitParser>(&self, parser: &G, text: &str) -> Result<Unit, UnitError> {
+        let mut unit = parser.parse_unit(text)?;
+
+        for pu in unit.partials.iter_mut() {
+            self.fill(pu).await.map_err(|e| e.at(pu))?;
+        }
+
+        Ok(unit)
+    }
+}
+
+impl<T: AsyncUnitQuery> AsyncUnitConverter for MainConverter<T> {
+    async fn is_valid_unit(&self, unit: &Unit) -> Result<bool, UnitError> {
+        for partial in &unit.partials {
+            if self.is_listed(&partial.name).await? {
+                continue;
+            }
+            // Resolved the way conversions will, prefixes and aliases included
+            match definition::resolve_async(&*self.query, partial).await {
+                Ok(_) => {}
+                Err(e) if e.is_unknown(partial) => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    async fn are_same_dimension(
+        &self,
+        unit1: &Unit,
+        unit2: &Unit,
+    ) -> Result<(bool, Dimension, Dimension), UnitError> {
+        let d1 = self.get_dimension(unit1).await?;
+        let d2 = self.get_dimension(unit2).await?;
+        Ok((d1 == d2, d1, d2))
+    }
+
+    async fn get_dimension(&self, unit: &Unit) -> Result<Dimension, UnitError> {
+        let mut dimension = Dimension::default();
+        for partial in &unit.partials {
+            let (_, dim) = definition::resolve_async(&*self.query, partial).await?;
+            dimension = dimension.dot(&dim, partial.exp())?;
+        }
+        Ok(dimension)
+    }
+
+    async fn get_dimension_mut(&self, unit: &mut Unit) -> Result<Dimension, UnitError> {
+        let dimension = self.get_dimension(unit).await?;
+        for partial in unit.partials.iter_mut() {
+            partial.dim = match self.query.get_dimension_name(partial).await {
+                Ok(name) => Some(name),
+                // Units known only by their definition have no dimension name
+                Err(UnitError::NoStoredDimension { .. }) => None,
+                Err(e) => return Err(e),
+            };
+        }
+        Ok(dimension)
+    }
+
+    async fn convert(&self, val: &Value, unit: &Unit) -> Result<Value, UnitError> {
+        let (same, d1, d2) = self.are_same_dimension(&val.unit, unit).await?;
+        if !same {
+            return Err(UnitError::BadDimension(d1, d2));
+        }
+        let cf1 = self.get_conversion_factor(&val.unit).await?;
+        let cf2 = self.get_conversion_factor(unit).await?;
+        let offset1 = self.get_conversion_offset(&val.unit).await?;
+        let offset2 = self.get_conversion_offset(unit).await?;
+        Ok(Value::from_value(
+            unit.clone(),
+            (val.value * cf1 + offset1 - offset2) / cf2,
+        ))
+    }
+
+    async fn get_conversion_factor(&self, unit: &Unit) -> Result<f64, UnitError> {
+        let mut cf = 1.;
+        for partial in &unit.partials {
+            let (c, _) = definition::resolve_async(&*self.query, partial).await?;
+            cf *= c.powf(partial.exp().to_f64());
+        }
+        Ok(cf)
+    }
+
+    // Offsets only make sense for an absolute temperature (`degC`), as soon as the unit is
+    // raised to a power or combined (`degC/m`, `degC^2`) it is a difference and scales only.
+    async fn get_conversion_offset(&self, unit: &Unit) -> Result<f64, UnitError> {
+        match unit.partials.as_slice() {
+            [partial] if partial.exp() == Rational::ONE => {
+                self.query.get_conversion_offset(partial).await
+            }
+            _ => Ok(0.),
+        }
+    }
+
+    async fn simplify(&self, unit: &Unit) -> Result<Unit, UnitError> {
+        let unit = unit.simplify()?;
+        if unit.partials.len() < 2 {
+            return Ok(unit);
+        }
+        let target = (
+            self.get_conversion_factor(&unit).await?,
+            self.get_dimension(&unit).await?,
+        );
+        let named = definition::named_unit_async(&*self.query, target).await?;
+        Ok(named.map_or(unit, Unit::from))
+    }
+
+    async fn simplify_value(&self, value: &Value) -> Result<Value, UnitError> {
+        let unit = value.unit.simplify()?;
+        if unit.partials.len() >= 2 {
+            let factor = self.get_conversion_factor(&unit).await?;
+            let target = (1., self.get_dimension(&unit).await?);
+            if let Some(named) = definition::named_unit_async(&*self.query, target).await? {
+                return Ok(Value::from_value(named.into(), value.value * factor));
+            }
+        }
+        Ok(Value::from_value(self.simplify(&unit).await?, value.value))
+    }
+
+    async fn add(&self, lhs: &Value, rhs: &Value) -> Result<Value, UnitError> {
+        let rhs = difference_in(self, rhs, &lhs.unit).await?;
+        Ok(Value::from_value(lhs.unit.clone(), lhs.value + rhs))
+    }
+
+    async fn sub(&self, lhs: &Value, rhs: &Value) -> Result<Value, UnitError> {
+        let rhs = difference_in(self, rhs, &lhs.unit).await?;
+        Ok(Value::from_value(lhs.unit.clone(), lhs.value - rhs))
+    }
+}
+
+/// Number of `unit` in the difference `value`, offsets aside.
+async fn difference_in<C: AsyncUnitConverter>(
+    converter: &C,
+    value: &Value,
+    unit: &Unit,
+) -> Result<f64, UnitError> {
+    if value.unit == *unit {
+        return Ok(value.value);
+    }
+    // Reported in the order of the operands, `unit` being the one of the left-hand side
+    match converter.are_same_dimension(unit, &value.unit).await? {
+        (true, _, _) => {
+            let factor = converter.get_conversion_factor(&value.unit).await?
+                / converter.get_conversion_factor(unit).await?;
+            Ok(value.value * factor)
+        }
+        (false, d1, d2) => Err(UnitError::BadDimension(d1, d2)),
+    }
+}
+
+#[cfg(all(test, feature = "embedded"))]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        InlineUnitParser,
+        unitquery::{MemoryUnitQuery, OverlayUnitQuery, SqlUnitQuery, UnitRegistry},
+    };
+
+    #[tokio::test]
+    async fn test_async_factory() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let factory = MainUnitFactory::new(c);
+        let pu = factory.construct_unit("degC", 1).await.unwrap();
+        assert!(pu.dim == Some("temperature".to_owned()));
+        assert!(pu.get_offset() == 273.15);
+
+        let parser = InlineUnitParser::default();
+        let unit = factory.parse_fill(&parser, "km/h").await.unwrap();
+        assert!(unit.partials[0].get_factor() == 1e3);
+        assert!(factory.parse_fill(&parser, "FALSEUNIT").await.is_err());
+        let e = factory.parse_fill(&parser, "km/hh").await.err().unwrap();
+        assert!(matches!(&e, UnitError::BadUnit { name, .. } if name == "hh"));
+        assert!(e.span() == Some(3..5));
+        let e = factory.parse_fill(&parser, "Kg*m").await.err().unwrap();
+        assert!(e.to_string().ends_with("did you mean kg?"));
+    }
+
+    #[tokio::test]
+    async fn test_async_convert() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let converter = MainConverter::new(c);
+        let kg: Unit = ElementUnit::new("kg", 1).into();
+        let m: Unit = ElementUnit::new("m", 1).into();
+        let kelvin: Unit = ElementUnit::new("K", 1).into();
+
+        let value = Value::from_value(ElementUnit::new("g", 1).into(), 5.0);
+        assert!(converter.convert(&value, &kg).await.unwrap().value == 5e-3);
+        let res = converter.convert(&value, &m).await;
+        assert!(matches!(res, Err(UnitError::BadDimension(..))));
+
+        let value = Value::from_value(ElementUnit::new("degC", 1).into(), 0.);
+        assert!(converter.convert(&value, &kelvin).await.unwrap().value == 273.15);
+
+        // Units declared by their definition only
+        let joule: Unit = ElementUnit::new("J", 1).into();
+        let value = Value::from_value(ElementUnit::new("kWh", 1).into(), 1.);
+        let res = converter.convert(&value, &joule).await.unwrap().value;
+        assert!((res - 3.6e6).abs() < 1e-6);
+        let power = Unit::from_vec(vec![ElementUnit::new("J", 1), ElementUnit::new("s", -1)]);
+        let power = converter.simplify(&power).await.unwrap();
+        assert!(power == ElementUnit::new("W", 1).into());
+        let sum = converter
+            .add(&Value::from_value(kg.clone(), 1.), &value)
+            .await;
+        assert!(matches!(sum, Err(UnitError::BadDimension(..))));
+        let grams = Value::from_value(ElementUnit::new("g", 1).into(), 250.);
+        let sum = converter.sub(&Value::from_value(kg, 1.), &grams).await;
+        assert!(sum.unwrap().value == 0.75);
+
+        // Unknown units are reported instead of panicking
+        let unknown: Unit = ElementUnit::new("FALSEUNIT", 1).into();
+        assert!(converter.get_dimension(&unknown).await.is_err());
+        assert!(!converter.is_valid_unit(&unknown).await.unwrap());
+        for name in ["mmol", "meter", "metres", "µm", "pascal"] {
+            let unit = ElementUnit::new(name, 1).into();
+            assert!(converter.is_valid_unit(&unit).await.unwrap(), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unit_list_revision() {
+        let path = std::env::temp_dir().join(format!("runit-ulist-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let base = SqlUnitQuery::temporary("ulist").await;
+        let c = Arc::new(OverlayUnitQuery::create(base, &path).await.unwrap());
+        let converter = MainConverter::new(c.clone());
+        let nibble: Unit = ElementUnit::new("nibble", 1).into();
+        assert!(!converter.is_valid_unit(&nibble).await.unwrap());
+        c.define_unit("nibble", "bit", 4.).await.unwrap();
+        assert!(converter.is_valid_unit(&nibble).await.unwrap());
+        c.remove_unit("nibble").await.unwrap();
+        assert!(!converter.is_valid_unit(&nibble).await.unwrap());
+    }
+}
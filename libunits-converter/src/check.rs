@@ -0,0 +1,316 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Integrity check of a catalogue, beyond what opening it verifies.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    DefinitionIssue, Dimension,
+    definition::{compare, resolve_definition_async},
+    unitquery::AsyncUnitQuery,
+};
+
+/// Problem found in a catalogue by [`SqlUnitQuery::check`](crate::unitquery::SqlUnitQuery::check).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Diagnostic {
+    /// `dimension_name` of the unit is not in the `dimension` table.
+    UnknownDimension {
+        unit: String,
+        dimension: String,
+    },
+    /// Unit with neither a dimension nor a definition to derive it from.
+    MissingDimension {
+        unit: String,
+    },
+    /// `unit_name` used by several rows, `unit_name` is not a key of the table.
+    DuplicateUnit {
+        unit: String,
+        count: usize,
+    },
+    DuplicateDimension {
+        dimension: String,
+        count: usize,
+    },
+    /// Exponents which are not fractions.
+    BadExponents {
+        dimension: String,
+        reason: String,
+    },
+    /// Factor which is not a positive finite number, or missing without a definition.
+    BadFactor {
+        unit: String,
+        factor: Option<f64>,
+    },
+    BadOffset {
+        unit: String,
+        offset: f64,
+    },
+    /// Alias of a unit missing from the catalogue.
+    UnknownAlias {
+        alias: String,
+        unit: String,
+    },
+    /// Alias used several times or also a unit name, `count` is the number of rows using it.
+    DuplicateAlias {
+        alias: String,
+        count: usize,
+    },
+    /// Stored factor or dimension disagreeing with the definition of the unit.
+    Definition(DefinitionIssue),
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownDimension { unit, dimension } => {
+                write!(f, "{}: unknown dimension {}", unit, dimension)
+            }
+            Self::MissingDimension { unit } => {
+                write!(f, "{}: no dimension and no definition", unit)
+            }
+            Self::DuplicateUnit { unit, count } => {
+                write!(f, "{}: unit defined {} times", unit, count)
+            }
+            Self::DuplicateDimension { dimension, count } => {
+                write!(f, "{}: dimension defined {} times", dimension, count)
+            }
+            Self::BadExponents { dimension, reason } => write!(f, "{}: {}", dimension, reason),
+            Self::BadFactor {
+                unit,
+                factor: Some(factor),
+            } => write!(f, "{}: factor {} is not positive", unit, factor),
+            Self::BadFactor { unit, factor: None } => {
+                write!(f, "{}: no factor and no definition", unit)
+            }
+            Self::BadOffset { unit, offset } => {
+                write!(f, "{}: offset {} is not finite", unit, offset)
+            }
+            Self::UnknownAlias { alias, unit } => {
+                write!(f, "{}: alias of unknown unit {}", alias, unit)
+            }
+            Self::DuplicateAlias { alias, count } => {
+                write!(f, "{}: alias used by {} names", alias, count)
+            }
+            Self::Definition(issue) => issue.fmt(f),
+        }
+    }
+}
+
+/// `conversiontable` row as stored, `NULL` cells are `None`.
+pub(crate) struct UnitRow {
+    pub name: String,
+    pub dimension: Option<String>,
+    pub factor: Option<f64>,
+    pub offset: f64,
+    pub definition: Option<String>,
+}
+
+/// `dimension` row, exponents failing to read as fractions give the reason.
+pub(crate) struct DimensionRow {
+    pub name: String,
+    pub dimension: Result<Dimension, String>,
+}
+
+fn duplicates<'a>(names: impl Iterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut order = Vec::new();
+    for name in names {
+        let count = counts.entry(name).or_default();
+        if *count == 0 {
+            order.push(name);
+        }
+        *count += 1;
+    }
+    order
+        .into_iter()
+        .filter(|name| counts[name] > 1)
+        .map(|name| (name, counts[name]))
+        .collect()
+}
+
+/// Checks made on the rows alone, definitions are resolved by the caller. `aliases` are (alias,
+/// unit) pairs.
+pub(crate) fn check_rows(
+    units: &[UnitRow],
+    dimensions: &[DimensionRow],
+    aliases: &[(String, String)],
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (dimension, count) in duplicates(dimensions.iter().map(|d| d.name.as_str())) {
+        let dimension = dimension.to_owned();
+        diagnostics.push(Diagnostic::DuplicateDimension { dimension, count });
+    }
+    for row in dimensions {
+        if let Err(reason) = &row.dimension {
+            diagnostics.push(Diagnostic::BadExponents {
+                dimension: row.name.clone(),
+                reason: reason.clone(),
+            });
+        }
+    }
+
+    for (unit, count) in duplicates(units.iter().map(|u| u.name.as_str())) {
+        let unit = unit.to_owned();
+        diagnostics.push(Diagnostic::DuplicateUnit { unit, count });
+    }
+    let known: HashSet<&str> = dimensions.iter().map(|d| d.name.as_str()).collect();
+    for row in units {
+        let unit = row.name.clone();
+        let defined = row.definition.is_some();
+        match &row.dimension {
+            Some(dimension) if !known.contains(dimension.as_str()) => {
+                let dimension = dimension.clone();
+                diagnostics.push(Diagnostic::UnknownDimension { unit, dimension });
+            }
+            None if !defined => diagnostics.push(Diagnostic::MissingDimension { unit }),
+            _ => {}
+        }
+        let unit = row.name.clone();
+        match row.factor {
+            Some(factor) if !(factor.is_finite() && factor > 0.) => {
+                diagnostics.push(Diagnostic::BadFactor {
+                    unit,
+                    factor: Some(factor),
+                })
+            }
+            None if !defined => diagnostics.push(Diagnostic::BadFactor { unit, factor: None }),
+            _ => {}
+        }
+        if !row.offset.is_finite() {
+            diagnostics.push(Diagnostic::BadOffset {
+                unit: row.name.clone(),
+                offset: row.offset,
+            });
+        }
+    }
+
+    let alias_names: HashSet<&str> = aliases.iter().map(|(alias, _)| alias.as_str()).collect();
+    let names = aliases.iter().map(|(alias, _)| alias.as_str()).chain(
+        units
+            .iter()
+            .map(|u| u.name.as_str())
+            .filter(|name| alias_names.contains(name)),
+    );
+    for (alias, count) in duplicates(names) {
+        let alias = alias.to_owned();
+        diagnostics.push(Diagnostic::DuplicateAlias { alias, count });
+    }
+    let unit_names: HashSet<&str> = units.iter().map(|u| u.name.as_str()).collect();
+    for (alias, unit) in aliases {
+        if !unit_names.contains(unit.as_str()) {
+            diagnostics.push(Diagnostic::UnknownAlias {
+                alias: alias.clone(),
+                unit: unit.clone(),
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Every check over the rows of a catalogue, definitions being resolved through `query`.
+pub(crate) async fn check_catalogue<Q: AsyncUnitQuery + ?Sized>(
+    query: &Q,
+    units: &[UnitRow],
+    dimensions: &[DimensionRow],
+    aliases: &[(String, String)],
+) -> Vec<Diagnostic> {
+    let mut diagnostics = check_rows(units, dimensions, aliases);
+    let stored: HashMap<&str, Dimension> = dimensions
+        .iter()
+        .filter_map(|row| Some((row.name.as_str(), row.dimension.clone().ok()?)))
+        .collect();
+    for row in units {
+        let Some(definition) = &row.definition else {
+            continue;
+        };
+        let dimension = row.dimension.as_deref().and_then(|name| stored.get(name));
+        let resolved = resolve_definition_async(query, &row.name, definition).await;
+        diagnostics.extend(
+            compare(
+                &row.name,
+                row.factor.unwrap_or(0.),
+                dimension.copied(),
+                resolved,
+            )
+            .map(Diagnostic::Definition),
+        );
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn unit(name: &str, dimension: Option<&str>, factor: Option<f64>) -> UnitRow {
+        UnitRow {
+            name: name.to_owned(),
+            dimension: dimension.map(str::to_owned),
+            factor,
+            offset: 0.,
+            definition: None,
+        }
+    }
+
+    #[test]
+    fn test_check_rows() {
+        let dimensions = [
+            DimensionRow {
+                name: "mass".to_owned(),
+                dimension: Ok(Dimension::from([1, 0, 0, 0, 0, 0, 0])),
+            },
+            DimensionRow {
+                name: "length".to_owned(),
+                dimension: Err("length exponent NaN is not a fraction".to_owned()),
+            },
+        ];
+        let mut defined = unit("W", None, None);
+        defined.definition = Some("J/s".to_owned());
+        let units = [
+            unit("kg", Some("mass"), Some(1.)),
+            unit("g", Some("mass"), Some(1e-3)),
+            unit("g", Some("mass"), Some(-1.)),
+            unit("m", Some("lenght"), Some(1.)),
+            unit("x", None, None),
+            defined,
+        ];
+        let aliases = [
+            ("gram".to_owned(), "g".to_owned()),
+            ("kg".to_owned(), "g".to_owned()),
+            ("metre".to_owned(), "meter".to_owned()),
+        ];
+        let diagnostics = check_rows(&units, &dimensions, &aliases);
+        assert!(diagnostics.len() == 8, "{:?}", diagnostics);
+        assert!(diagnostics.contains(&Diagnostic::DuplicateAlias {
+            alias: "kg".to_owned(),
+            count: 2
+        }));
+        assert!(diagnostics.contains(&Diagnostic::UnknownAlias {
+            alias: "metre".to_owned(),
+            unit: "meter".to_owned()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::DuplicateUnit {
+            unit: "g".to_owned(),
+            count: 2
+        }));
+        assert!(diagnostics.contains(&Diagnostic::BadFactor {
+            unit: "g".to_owned(),
+            factor: Some(-1.)
+        }));
+        assert!(diagnostics.contains(&Diagnostic::UnknownDimension {
+            unit: "m".to_owned(),
+            dimension: "lenght".to_owned()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::MissingDimension {
+            unit: "x".to_owned()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::BadFactor {
+            unit: "x".to_owned(),
+            factor: None
+        }));
+        assert!(diagnostics.iter().any(
+            |d| matches!(d, Diagnostic::BadExponents { dimension, .. } if dimension == "length")
+        ));
+    }
+}
@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Text forms of units, each one read back by the default parser.
+
+use std::fmt::Write;
+
+use super::{ElementUnit, Rational, Unit, Value};
+
+/// Notation used to write a unit, `kg·m²/s²` in each of them:
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitStyle {
+    /// `kg*m^2*s^-2`, what [`Display`](std::fmt::Display) writes.
+    #[default]
+    Inline,
+    /// `kg·m²/s²`, what the alternate form `{:#}` writes. Fractional exponents stay inline
+    /// (`s^(1/2)`).
+    Fraction,
+    /// `\mathrm{kg\,m^{2}\,s^{-2}}`.
+    Latex,
+}
+
+const SUPERSCRIPTS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+fn superscript(exp: i32) -> String {
+    let sign = (exp < 0).then_some('⁻');
+    let digits = exp.unsigned_abs().to_string();
+    sign.into_iter()
+        .chain(
+            digits
+                .chars()
+                .map(|d| SUPERSCRIPTS[d.to_digit(10).unwrap() as usize]),
+        )
+        .collect()
+}
+
+/// `exp` after a unit name, nothing for 1.
+fn exponent(exp: Rational, style: UnitStyle) -> String {
+    if exp == Rational::from(1) {
+        return String::new();
+    }
+    match style {
+        UnitStyle::Fraction if exp.is_integer() => superscript(exp.numerator()),
+        UnitStyle::Inline if exp.is_integer() => format!("^{}", exp),
+        UnitStyle::Inline | UnitStyle::Fraction => format!("^({})", exp),
+        UnitStyle::Latex => format!("^{{{}}}", exp),
+    }
+}
+
+fn join<'a>(
+    partials: impl IntoIterator<Item = &'a ElementUnit>,
+    style: UnitStyle,
+    separator: &str,
+) -> String {
+    partials
+        .into_iter()
+        .map(|p| p.format_term(style))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+impl ElementUnit {
+    /// The unit as written in `style`, `kg`, `s^-2`, `s⁻²` or `\mathrm{s^{-2}}`.
+    pub fn format(&self, style: UnitStyle) -> String {
+        match style {
+            UnitStyle::Latex => format!("\\mathrm{{{}}}", self.format_term(style)),
+            _ => self.format_term(style),
+        }
+    }
+
+    fn format_term(&self, style: UnitStyle) -> String {
+        format!("{}{}", self.name, exponent(self.exp(), style))
+    }
+}
+
+impl Unit {
+    /// The unit as written in `style`, empty when it has no partial. The default parser reads it
+    /// back to an equal unit.
+    pub fn format(&self, style: UnitStyle) -> String {
+        match style {
+            UnitStyle::Inline => join(&self.partials, style, "*"),
+            UnitStyle::Latex if self.partials.is_empty() => String::new(),
+            UnitStyle::Latex => format!("\\mathrm{{{}}}", join(&self.partials, style, "\\,")),
+            UnitStyle::Fraction => {
+                let (numerator, denominator): (Vec<&ElementUnit>, Vec<&ElementUnit>) =
+                    self.partials.iter().partition(|p| p.exp() > Rational::ZERO);
+                // Without numerator the `/` would have nothing to divide, exponents stay negative
+                if numerator.is_empty() {
+                    return join(denominator, style, "·");
+                }
+                let mut text = join(numerator, style, "·");
+                let inverted: Vec<ElementUnit> = denominator
+                    .into_iter()
+                    .map(|p| {
+                        let mut p = p.clone();
+                        p.set_exp(-p.exp());
+                        p
+                    })
+                    .collect();
+                match inverted.len() {
+                    0 => {}
+                    1 => write!(text, "/{}", join(&inverted, style, "·")).unwrap(),
+                    _ => write!(text, "/({})", join(&inverted, style, "·")).unwrap(),
+                }
+                text
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ElementUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let style = match f.alternate() {
+            true => UnitStyle::Fraction,
+            false => UnitStyle::Inline,
+        };
+        f.write_str(&self.format(style))
+    }
+}
+
+impl std::fmt::Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let style = match f.alternate() {
+            true => UnitStyle::Fraction,
+            false => UnitStyle::Inline,
+        };
+        f.write_str(&self.format(style))
+    }
+}
+
+impl Value {
+    /// The number followed by its unit written in `style`, `5 kg*m^-3`.
+    pub fn format(&self, style: UnitStyle) -> String {
+        match self.unit.format(style) {
+            unit if unit.is_empty() => self.value.to_string(),
+            unit => format!("{} {}", self.value, unit),
+        }
+    }
+}
+
+/// The number honours the precision, `{:.2}` writes `0.50 kg`, and `{:#}` the fraction style.
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*}", precision, self.value)?,
+            None => write!(f, "{}", self.value)?,
+        }
+        if !self.unit.partials.is_empty() {
+            write!(f, " ")?;
+            match f.alternate() {
+                true => write!(f, "{:#}", self.unit)?,
+                false => write!(f, "{}", self.unit)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{InlineUnitParser, UnitParser};
+
+    const STYLES: [UnitStyle; 3] = [UnitStyle::Inline, UnitStyle::Fraction, UnitStyle::Latex];
+
+    fn unit(partials: &[(&str, Rational)]) -> Unit {
+        Unit::from_vec(
+            partials
+                .iter()
+                .map(|(name, exp)| ElementUnit::new(name, *exp))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_styles() {
+        let energy = unit(&[
+            ("kg", Rational::from(1)),
+            ("m", Rational::from(2)),
+            ("s", Rational::from(-2)),
+        ]);
+        assert!(energy.to_string() == "kg*m^2*s^-2");
+        assert!(format!("{:#}", energy) == "kg·m²/s²");
+        assert!(energy.format(UnitStyle::Latex) == "\\mathrm{kg\\,m^{2}\\,s^{-2}}");
+
+        let noise = unit(&[
+            ("V", Rational::from(1)),
+            ("s", Rational::new(1, 2)),
+            ("m", Rational::from(-1)),
+            ("K", Rational::from(-1)),
+        ]);
+        assert!(noise.to_string() == "V*s^(1/2)*m^-1*K^-1");
+        assert!(format!("{:#}", noise) == "V·s^(1/2)/(m·K)");
+        assert!(noise.format(UnitStyle::Latex) == "\\mathrm{V\\,s^{1/2}\\,m^{-1}\\,K^{-1}}");
+
+        let frequency = unit(&[("s", Rational::from(-1))]);
+        assert!(format!("{:#}", frequency) == "s⁻¹");
+
+        let value = Value::from_value(energy, 0.5);
+        assert!(value.to_string() == "0.5 kg*m^2*s^-2");
+        assert!(format!("{:.2}", value) == "0.50 kg*m^2*s^-2");
+        assert!(Value::from_value(unit(&[]), 2.).to_string() == "2");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let parser = InlineUnitParser::default();
+        for text in [
+            "kg*m^2*s^-2",
+            "m/s",
+            "(kg*m)/(s^2*A)",
+            "Pa^-5",
+            "µm^(3/2)/h",
+            "degC",
+        ] {
+            let unit = parser.parse_unit(text).unwrap();
+            for style in STYLES {
+                let written = unit.format(style);
+                assert!(parser.parse_unit(&written).unwrap() == unit, "{}", written);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn fuzzy_round_trip(
+            partials in prop::collection::vec(("[a-zA-Z]{1,4}", -6i32..6, 1i32..4), 1..5),
+        ) {
+            let parser = InlineUnitParser::default();
+            let unit = Unit::from_vec(
+                partials
+                    .iter()
+                    .filter(|(_, num, _)| *num != 0)
+                    .map(|(name, num, den)| ElementUnit::new(name, Rational::new(*num, *den)))
+                    .collect(),
+            );
+            prop_assume!(!unit.partials.is_empty());
+            for style in STYLES {
+                let written = unit.format(style);
+                let parsed = parser.parse_unit(&written);
+                prop_assert!(parsed.is_ok(), "{}", written);
+                prop_assert!(parsed.unwrap() == unit, "{}", written);
+            }
+        }
+    }
+}
@@ -1,22 +1,37 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::fmt::write;
+use std::ops::Range;
 
+use crate::UnitError;
+
+mod format;
+mod ops;
+mod rational;
+pub use format::UnitStyle;
+pub use rational::Rational;
+
+/// Units are equal when written the same: name and exponent, whatever was looked up in the
+/// catalogue and wherever they were parsed from.
 #[derive(Clone, Debug)]
 pub struct ElementUnit {
     pub name: String,
-    exp: f64,
+    exp: Rational,
     pub dim: Option<String>,
     conversionfactor: f64,
+    conversionoffset: f64,
+    /// Byte range of the name in the text it was parsed from.
+    span: Option<Range<usize>>,
 }
 
 impl ElementUnit {
-    pub fn new(name: &str, exp: f64) -> Self {
+    pub fn new(name: &str, exp: impl Into<Rational>) -> Self {
         Self {
             name: name.to_owned(),
-            exp,
+            exp: exp.into(),
             dim: None,
             conversionfactor: 0.,
+            conversionoffset: 0.,
+            span: None,
         }
     }
 
@@ -28,28 +43,90 @@ impl ElementUnit {
         self.conversionfactor
     }
 
-    pub fn exp(&self) -> f64 {
+    pub fn set_offset(&mut self, offset: f64) {
+        self.conversionoffset = offset;
+    }
+
+    pub fn get_offset(&self) -> f64 {
+        self.conversionoffset
+    }
+
+    pub fn exp(&self) -> Rational {
         self.exp
     }
 
-    pub fn set_exp(&mut self, exp: f64) {
+    pub fn set_exp(&mut self, exp: Rational) {
         self.exp = exp;
     }
 
     pub fn set_dim(&mut self, dim: &str) {
         self.dim = Some(dim.to_owned());
     }
+
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+
+    pub fn set_span(&mut self, span: Option<Range<usize>>) {
+        self.span = span;
+    }
+}
+
+impl PartialEq for ElementUnit {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.exp == other.exp
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Unit {
     pub partials: Vec<ElementUnit>,
 }
 
+/// Units are equal when made of the same partials, in any order: `kg*m` is `m*kg`.
+impl PartialEq for Unit {
+    fn eq(&self, other: &Self) -> bool {
+        let count = |unit: &Self, p: &ElementUnit| unit.partials.iter().filter(|q| *q == p).count();
+        self.partials.len() == other.partials.len()
+            && self
+                .partials
+                .iter()
+                .all(|p| count(self, p) == count(other, p))
+    }
+}
+
 impl Unit {
     pub fn from_vec(partials: Vec<ElementUnit>) -> Self {
         Self { partials }
     }
+
+    /// Same unit written in canonical form: partials of the same name merged into one (the first
+    /// keeps its catalogue data), those cancelled to a zero exponent dropped, and the rest
+    /// ordered by name, positive exponents first (`s^-1*kg*s*kg` is `kg^2`).
+    ///
+    /// Different units of one dimension are left apart, `kg*g` is not `kg^2`, see
+    /// [`UnitConverter::simplify`](crate::UnitConverter::simplify) for a catalogue aware form.
+    /// Fails with [`UnitError::Overflow`] when merged exponents leave the range of `i32`.
+    pub fn simplify(&self) -> Result<Self, UnitError> {
+        let mut partials: Vec<ElementUnit> = Vec::with_capacity(self.partials.len());
+        for partial in &self.partials {
+            match partials.iter_mut().find(|p| p.name == partial.name) {
+                Some(merged) => {
+                    merged.set_exp(merged.exp().checked_add(partial.exp())?);
+                    // Spread over several places of the text
+                    merged.set_span(None);
+                }
+                None => partials.push(partial.clone()),
+            }
+        }
+        partials.retain(|p| p.exp() != Rational::ZERO);
+        partials.sort_by(|a, b| {
+            (a.exp() < Rational::ZERO)
+                .cmp(&(b.exp() < Rational::ZERO))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Ok(Self { partials })
+    }
 }
 
 impl From<ElementUnit> for Unit {
@@ -60,6 +137,7 @@ impl From<ElementUnit> for Unit {
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct Value {
     pub unit: Unit,
     pub value: f64,
@@ -72,76 +150,75 @@ impl From<Value> for f64 {
 }
 
 impl Value {
-    fn new(unit: Unit) -> Self {
-        Self { unit, value: 0. }
-    }
     pub fn from_value(unit: Unit, value: f64) -> Self {
         Self { unit, value }
     }
 }
 
-impl std::fmt::Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.value)
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Dimension(pub [Rational; 7]);
+
+impl From<[i32; 7]> for Dimension {
+    fn from(value: [i32; 7]) -> Self {
+        Self(value.map(Rational::from))
     }
 }
 
-#[derive(Default, Clone, Copy, Debug)]
-pub struct Dimension(pub [i32; 7]);
-
 impl std::fmt::Display for Dimension {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.0)
+        write!(f, "[")?;
+        for (i, d) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", d)?;
+        }
+        write!(f, "]")
     }
 }
 use std::fmt::Write;
 
 impl Dimension {
-    pub fn dot(&self, val: &Self, exp: f64) -> Self {
-        let e: [i32; 7] = self
-            .0
-            .iter()
-            .zip(val.0.iter())
-            .map(|(di, dj)| *di + ((*dj as f64) * exp) as i32)
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
-        Self(e)
+    pub const NAMES: [&str; 7] = [
+        "mass",
+        "duration",
+        "length",
+        "temperature",
+        "current",
+        "amount",
+        "luminosity",
+    ];
+
+    /// All exponents zero: a ratio (`m/km`), an angle or a count.
+    pub fn is_dimensionless(&self) -> bool {
+        self.0.iter().all(Rational::is_zero)
+    }
+
+    /// `self` times `val` raised to `exp`, [`UnitError::Overflow`] when an exponent leaves the
+    /// range of `i32`.
+    pub fn dot(&self, val: &Self, exp: Rational) -> Result<Self, UnitError> {
+        let mut e = self.0;
+        for (di, dj) in e.iter_mut().zip(val.0.iter()) {
+            *di = di.checked_add(dj.checked_mul(exp)?)?;
+        }
+        Ok(Self(e))
     }
 
     pub fn pretty_print(&self) -> String {
-        const NAMES: [&str; 7] = [
-            "mass",
-            "duration",
-            "length",
-            "temperature",
-            "current",
-            "amount",
-            "luminosity",
-        ];
-
         let mut f = String::new();
 
         // Header row
-        for name in NAMES.iter() {
+        for name in Self::NAMES.iter() {
             write!(f, "{:<12}", name).unwrap(); // left-align in 12-char width
         }
         writeln!(f).unwrap();
 
         // Data row
         for val in self.0.iter() {
-            write!(f, "{:<12.3}", val).unwrap(); // 3 decimal float
+            write!(f, "{:<12}", val).unwrap();
         }
         writeln!(f).unwrap(); // line break
 
         f
     }
 }
-
-impl PartialEq for Dimension {
-    fn eq(&self, other: &Self) -> bool {
-        self.0.iter().zip(other.0.iter()).all(|(d1, d2)| d1 == d2)
-    }
-}
-
-impl Eq for Dimension {}
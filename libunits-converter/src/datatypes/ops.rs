@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Products and powers of units and values. They only combine partials and never need the
+//! catalogue, sums do and are made by [`UnitConverter::add`](crate::UnitConverter::add).
+
+use std::ops::{Div, Mul, Neg};
+
+use super::{Rational, Unit, Value};
+use crate::UnitError;
+
+impl Unit {
+    /// Every exponent multiplied by `exp`, `m/s` to the power 2 is `m^2/s^2`. Fails with
+    /// [`UnitError::Overflow`] when an exponent leaves the range of `i32`.
+    pub fn pow(&self, exp: Rational) -> Result<Self, UnitError> {
+        let mut partials = self.partials.clone();
+        for p in partials.iter_mut() {
+            p.set_exp(p.exp().checked_mul(exp)?);
+        }
+        Self::from_vec(partials).simplify()
+    }
+}
+
+/// Partials of both units, merged by [`Unit::simplify`]: `kg*m` times `m/s` is `kg*m^2*s^-1`.
+/// Fails as [`Unit::simplify`] does.
+impl Mul for Unit {
+    type Output = Result<Self, UnitError>;
+
+    fn mul(mut self, rhs: Self) -> Result<Self, UnitError> {
+        self.partials.extend(rhs.partials);
+        self.simplify()
+    }
+}
+
+impl Div for Unit {
+    type Output = Result<Self, UnitError>;
+
+    fn div(self, rhs: Self) -> Result<Self, UnitError> {
+        self * rhs.pow(-Rational::ONE)?
+    }
+}
+
+impl Value {
+    /// Value raised to `exp`, its unit as well.
+    pub fn powi(&self, exp: i32) -> Result<Self, UnitError> {
+        let unit = self.unit.pow(Rational::reduce(exp.into(), 1)?)?;
+        Ok(Self::from_value(unit, self.value.powi(exp)))
+    }
+
+    /// Value raised to `exp`, a fraction so that the exponents of the unit stay exact (`m^(1/2)`).
+    pub fn powf(&self, exp: Rational) -> Result<Self, UnitError> {
+        let unit = self.unit.pow(exp)?;
+        Ok(Self::from_value(unit, self.value.powf(exp.to_f64())))
+    }
+
+    pub fn sqrt(&self) -> Result<Self, UnitError> {
+        let unit = self.unit.pow(Rational::new(1, 2))?;
+        Ok(Self::from_value(unit, self.value.sqrt()))
+    }
+}
+
+/// Product of the numbers and of the units, `5 bar * 2 L` is `10 L*bar`. Fails as the product
+/// of the units does.
+impl Mul for Value {
+    type Output = Result<Self, UnitError>;
+
+    fn mul(self, rhs: Self) -> Result<Self, UnitError> {
+        Ok(Self::from_value(
+            (self.unit * rhs.unit)?,
+            self.value * rhs.value,
+        ))
+    }
+}
+
+impl Div for Value {
+    type Output = Result<Self, UnitError>;
+
+    fn div(self, rhs: Self) -> Result<Self, UnitError> {
+        Ok(Self::from_value(
+            (self.unit / rhs.unit)?,
+            self.value / rhs.value,
+        ))
+    }
+}
+
+impl Mul<f64> for Value {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self::from_value(self.unit, self.value * rhs)
+    }
+}
+
+impl Mul<Value> for f64 {
+    type Output = Value;
+
+    fn mul(self, rhs: Value) -> Value {
+        rhs * self
+    }
+}
+
+impl Div<f64> for Value {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self {
+        Self::from_value(self.unit, self.value / rhs)
+    }
+}
+
+impl Neg for Value {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::from_value(self.unit, -self.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{InlineUnitParser, UnitParser};
+
+    fn value(value: f64, unit: &str) -> Value {
+        Value::from_value(InlineUnitParser::default().parse_unit(unit).unwrap(), value)
+    }
+
+    #[test]
+    fn test_value_ops() {
+        let work = (value(5., "bar") * value(2., "L")).unwrap();
+        assert!(work.to_string() == "10 L*bar");
+        let speed = (value(3., "km") / value(20., "min")).unwrap();
+        assert!(speed.to_string() == "0.15 km*min^-1");
+        let distance = (speed.clone() * value(2., "min")).unwrap();
+        assert!(distance.to_string() == "0.3 km");
+        assert!((-(2. * speed / 4.)).to_string() == "-0.075 km*min^-1");
+
+        assert!(value(3., "m/s").powi(2).unwrap().to_string() == "9 m^2*s^-2");
+        assert!(value(3., "m/s").powi(0).unwrap().to_string() == "1");
+        let side = value(16., "m^2").sqrt().unwrap();
+        assert!(side.value == 4. && side.unit == value(1., "m").unit);
+        let noise = value(8., "V^2/Hz").powf(Rational::new(1, 2)).unwrap();
+        assert!(noise.to_string() == "2.8284271247461903 V*Hz^(-1/2)");
+        let unit = (value(1., "kg*m").unit / value(1., "s*m").unit).unwrap();
+        assert!(unit.to_string() == "kg*s^-1");
+    }
+
+    #[test]
+    fn test_overflow() {
+        let big = Rational::from(46341);
+        let square = value(1., "m").powf(big).unwrap();
+        assert!(matches!(square.powf(big), Err(UnitError::Overflow(_))));
+        let max = value(1., "m").powi(i32::MAX).unwrap();
+        assert!((max.clone() * value(1., "m")).is_err());
+        assert!((max / value(1., "m^-1")).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::Ordering;
+use std::ops::Neg;
+use std::str::FromStr;
+
+use crate::UnitError;
+
+/// Exact fraction used for unit exponents and dimensions, always stored reduced with a positive
+/// denominator so that derived equality is the mathematical one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i32,
+    den: i32,
+}
+
+const fn gcd(mut a: i64, mut b: i64) -> i64 {
+    while b != 0 {
+        let t = b;
+        b = a % b;
+        a = t;
+    }
+    a.abs()
+}
+
+impl Rational {
+    pub const ZERO: Self = Self { num: 0, den: 1 };
+    pub const ONE: Self = Self { num: 1, den: 1 };
+
+    /// # Panics
+    /// Panics if `den` is zero or if either is `i32::MIN`.
+    pub const fn new(num: i32, den: i32) -> Self {
+        assert!(den != 0, "Rational with null denominator");
+        assert!(num != i32::MIN && den != i32::MIN, "Rational out of range");
+        let (num, den) = (num as i64, den as i64);
+        let g = gcd(num, den);
+        let sign = if den < 0 { -1 } else { 1 };
+        // Both fit: reducing and flipping signs can only bring them closer to zero
+        Self {
+            num: (sign * num / g) as i32,
+            den: (sign * den / g) as i32,
+        }
+    }
+
+    /// `num / den` reduced, [`UnitError::Overflow`] when it does not fit. `i32::MIN` is left out
+    /// so that negating a fraction never overflows.
+    pub(crate) fn reduce(num: i64, den: i64) -> Result<Self, UnitError> {
+        let g = gcd(num, den);
+        let sign = if den < 0 { -1 } else { 1 };
+        let (num, den) = (sign * num / g, sign * den / g);
+        let fit = |n: i64| i32::try_from(n).ok().filter(|n| *n != i32::MIN);
+        match (fit(num), fit(den)) {
+            (Some(num), Some(den)) => Ok(Self { num, den }),
+            _ => Err(UnitError::Overflow(format!("{}/{}", num, den))),
+        }
+    }
+
+    /// `self + rhs`, [`UnitError::Overflow`] when the sum does not fit in an `i32` fraction.
+    pub fn checked_add(self, rhs: Self) -> Result<Self, UnitError> {
+        Self::reduce(
+            self.num as i64 * rhs.den as i64 + rhs.num as i64 * self.den as i64,
+            self.den as i64 * rhs.den as i64,
+        )
+    }
+
+    /// `self - rhs`, see [`Rational::checked_add`].
+    pub fn checked_sub(self, rhs: Self) -> Result<Self, UnitError> {
+        self.checked_add(-rhs)
+    }
+
+    /// `self * rhs`, see [`Rational::checked_add`].
+    pub fn checked_mul(self, rhs: Self) -> Result<Self, UnitError> {
+        Self::reduce(
+            self.num as i64 * rhs.num as i64,
+            self.den as i64 * rhs.den as i64,
+        )
+    }
+
+    /// `self / rhs`, see [`Rational::checked_add`].
+    ///
+    /// # Panics
+    /// Panics if `rhs` is zero.
+    pub fn checked_div(self, rhs: Self) -> Result<Self, UnitError> {
+        assert!(rhs.num != 0, "Rational division by zero");
+        Self::reduce(
+            self.num as i64 * rhs.den as i64,
+            self.den as i64 * rhs.num as i64,
+        )
+    }
+
+    pub fn numerator(&self) -> i32 {
+        self.num
+    }
+
+    pub fn denominator(&self) -> i32 {
+        self.den
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Closest fraction with a denominator up to 1000, `None` if `value` is not one of them
+    /// (database columns are stored as `double`).
+    pub fn from_f64(value: f64) -> Option<Self> {
+        const MAX_DEN: i64 = 1000;
+        if !value.is_finite() || value.abs() > i32::MAX as f64 {
+            return None;
+        }
+        // Continued fraction expansion, stops as soon as the convergent is exact enough
+        let (mut h0, mut h1) = (0i64, 1i64);
+        let (mut k0, mut k1) = (1i64, 0i64);
+        let mut x = value;
+        loop {
+            let a = x.floor();
+            let (h2, k2) = (a as i64 * h1 + h0, a as i64 * k1 + k0);
+            if k2 > MAX_DEN {
+                return None;
+            }
+            (h0, h1, k0, k1) = (h1, h2, k1, k2);
+            if (h1 as f64 / k1 as f64 - value).abs() < 1e-9 {
+                return Self::reduce(h1, k1).ok();
+            }
+            x = 1. / (x - a);
+        }
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+/// # Panics
+/// Panics on `i32::MIN`, as [`Rational::new`] does.
+impl From<i32> for Rational {
+    fn from(value: i32) -> Self {
+        Self::new(value, 1)
+    }
+}
+
+impl From<Rational> for f64 {
+    fn from(value: Rational) -> Self {
+        value.to_f64()
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.num as i64 * other.den as i64).cmp(&(other.num as i64 * self.den as i64))
+    }
+}
+
+impl std::fmt::Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            f.pad(&self.num.to_string())
+        } else {
+            f.pad(&format!("{}/{}", self.num, self.den))
+        }
+    }
+}
+
+/// Accepts integers (`-2`), decimals (`0.25`) and fractions (`1/2`).
+impl FromStr for Rational {
+    type Err = UnitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || UnitError::parse(s, "bad exponent", None);
+        if let Some((num, den)) = s.split_once('/') {
+            let num: Rational = num.trim().parse()?;
+            let den: Rational = den.trim().parse()?;
+            if den.is_zero() {
+                return Err(bad());
+            }
+            return num.checked_div(den).map_err(|_| bad());
+        }
+
+        let (sign, digits) = match s.strip_prefix('-') {
+            Some(digits) => (-1, digits),
+            None => (1, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if (int.is_empty() && frac.is_empty())
+            || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(bad());
+        }
+        let int: i64 = if int.is_empty() {
+            0
+        } else {
+            int.parse().map_err(|_| bad())?
+        };
+        let frac_trimmed = frac.trim_end_matches('0');
+        if frac_trimmed.len() > 9 {
+            return Err(bad());
+        }
+        let scale = 10i64.pow(frac_trimmed.len() as u32);
+        let frac: i64 = if frac_trimmed.is_empty() {
+            0
+        } else {
+            frac_trimmed.parse().map_err(|_| bad())?
+        };
+        let num = int
+            .checked_mul(scale)
+            .and_then(|num| num.checked_add(frac))
+            .ok_or_else(bad)?;
+        Self::reduce(sign * num, scale).map_err(|_| bad())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rational;
+    use crate::UnitError;
+
+    #[test]
+    fn test_reduce() {
+        assert!(Rational::new(2, 4) == Rational::new(1, 2));
+        assert!(Rational::new(1, -2) == Rational::new(-1, 2));
+        assert!(Rational::new(0, 5) == Rational::ZERO);
+        assert!(Rational::new(-1, -3).numerator() == 1);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let half = Rational::new(1, 2);
+        let third = Rational::new(1, 3);
+        assert!(half.checked_add(third).unwrap() == Rational::new(5, 6));
+        assert!(half.checked_sub(third).unwrap() == Rational::new(1, 6));
+        assert!(half.checked_mul(third).unwrap() == Rational::new(1, 6));
+        assert!(half.checked_div(third).unwrap() == Rational::new(3, 2));
+        assert!(-half == Rational::new(-1, 2));
+        assert!(half > third);
+        assert!(half.checked_mul(Rational::from(2)).unwrap().is_integer());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let big = Rational::from(46341);
+        assert!(matches!(big.checked_mul(big), Err(UnitError::Overflow(_))));
+        let max = Rational::from(i32::MAX);
+        assert!(max.checked_add(Rational::ONE).is_err());
+        assert!(max.checked_add(-max).unwrap() == Rational::ZERO);
+        assert!(
+            Rational::new(1, 46341)
+                .checked_mul(Rational::new(1, 46341))
+                .is_err()
+        );
+        assert!("2147483648".parse::<Rational>().is_err());
+        assert!("2147483647/0.5".parse::<Rational>().is_err());
+        // Left out so that `-Rational::from(i32::MIN)` cannot overflow
+        assert!(std::panic::catch_unwind(|| Rational::from(i32::MIN)).is_err());
+    }
+
+    #[test]
+    fn test_parse_display() {
+        assert!("0.5".parse::<Rational>().unwrap() == Rational::new(1, 2));
+        assert!("-1/2".parse::<Rational>().unwrap() == Rational::new(-1, 2));
+        assert!("+3".parse::<Rational>().unwrap() == Rational::from(3));
+        assert!("2.50".parse::<Rational>().unwrap() == Rational::new(5, 2));
+        assert!("1/0".parse::<Rational>().is_err());
+        assert!("a".parse::<Rational>().is_err());
+        assert!("".parse::<Rational>().is_err());
+        assert!(Rational::new(-1, 2).to_string() == "-1/2");
+        assert!(Rational::from(3).to_string() == "3");
+    }
+
+    #[test]
+    fn test_from_f64() {
+        assert!(Rational::from_f64(-2.) == Some(Rational::from(-2)));
+        assert!(Rational::from_f64(0.5) == Some(Rational::new(1, 2)));
+        assert!(Rational::from_f64(1. / 3.) == Some(Rational::new(1, 3)));
+        assert!(Rational::from_f64(std::f64::consts::PI).is_none());
+    }
+}
@@ -0,0 +1,411 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Units defined by an expression over other units (`N` is `kg*m/s^2`, `kWh` is `1000*W*h`).
+//!
+//! A definition is an optional number followed by a unit expression in the default notation,
+//! a prefixed unit is defined as its prefix factor times the catalogue unit (`km` is `1000*m`).
+//! Units without a definition are base rows, their stored factor and dimension are used as is.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::{
+    Dimension, ElementUnit, InlineUnitParser, Unit, UnitError, UnitParser,
+    unitquery::{AsyncUnitQuery, UnitQuery},
+};
+
+/// Factor to SI and dimension of a unit.
+type Resolved = Result<(f64, Dimension), UnitError>;
+
+/// Relative difference tolerated between a stored factor and the one of its definition.
+const TOLERANCE: f64 = 1e-9;
+
+/// Splits a definition into its scale and unit expression, a bare number is dimensionless.
+pub fn parse_definition(definition: &str) -> Result<(f64, Unit), UnitError> {
+    let definition = definition.trim();
+    let end = definition
+        .find(|c: char| c == '*' || c.is_whitespace())
+        .unwrap_or(definition.len());
+    let (head, rest) = definition.split_at(end);
+    let scale = match head.chars().next() {
+        Some(c) if c.is_ascii_digit() || c == '.' => head
+            .parse::<f64>()
+            .map_err(|_| UnitError::parse(definition, "bad scale", Some(0..head.len())))?,
+        _ => return Ok((1., InlineUnitParser::default().parse_unit(definition)?)),
+    };
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix('*').unwrap_or(rest).trim_start();
+    if rest.is_empty() {
+        Ok((scale, Unit::from_vec(Vec::new())))
+    } else {
+        Ok((scale, InlineUnitParser::default().parse_unit(rest)?))
+    }
+}
+
+/// Factor to SI and dimension of `unit` (its exponent aside), following definitions down to
+/// base rows.
+pub(crate) fn resolve<Q: UnitQuery + ?Sized>(query: &Q, unit: &ElementUnit) -> Resolved {
+    resolve_in(query, &unit.name, &mut Vec::new())
+}
+
+fn resolve_in<Q: UnitQuery + ?Sized>(
+    query: &Q,
+    unit_name: &str,
+    stack: &mut Vec<String>,
+) -> Resolved {
+    check_cycle(stack, unit_name)?;
+    let unit = ElementUnit::new(unit_name, 1);
+    let Some(definition) = query.get_definition(&unit)? else {
+        let (_, dimension) = query.get_dimension(&unit)?;
+        return Ok((query.get_conversion_factor(&unit)?, dimension));
+    };
+    // Positions in the definition mean nothing to the caller
+    let (mut factor, unit) = parse_definition(&definition).map_err(UnitError::unlocated)?;
+    let mut dimension = Dimension::default();
+    stack.push(unit_name.to_owned());
+    for partial in &unit.partials {
+        let (f, d) = resolve_in(query, &partial.name, stack)?;
+        factor *= f.powf(partial.exp().to_f64());
+        dimension = dimension.dot(&d, partial.exp())?;
+    }
+    stack.pop();
+    Ok((factor, dimension))
+}
+
+/// See [`resolve`].
+pub(crate) async fn resolve_async<Q: AsyncUnitQuery + ?Sized>(
+    query: &Q,
+    unit: &ElementUnit,
+) -> Resolved {
+    resolve_in_async(query, &unit.name, &mut Vec::new()).await
+}
+
+fn resolve_in_async<'a, Q: AsyncUnitQuery + ?Sized>(
+    query: &'a Q,
+    unit_name: &'a str,
+    stack: &'a mut Vec<String>,
+) -> Pin<Box<dyn Future<Output = Resolved> + 'a>> {
+    Box::pin(async move {
+        check_cycle(stack, unit_name)?;
+        let unit = ElementUnit::new(unit_name, 1);
+        let Some(definition) = query.get_definition(&unit).await? else {
+            let (_, dimension) = query.get_dimension(&unit).await?;
+            return Ok((query.get_conversion_factor(&unit).await?, dimension));
+        };
+        stack.push(unit_name.to_owned());
+        let resolved = expand_async(query, &definition, stack).await;
+        stack.pop();
+        resolved
+    })
+}
+
+/// Factor and dimension of the unit `unit_name` as given by `definition`, whatever its row
+/// holds.
+pub(crate) async fn resolve_definition_async<Q: AsyncUnitQuery + ?Sized>(
+    query: &Q,
+    unit_name: &str,
+    definition: &str,
+) -> Resolved {
+    expand_async(query, definition, &mut vec![unit_name.to_owned()]).await
+}
+
+async fn expand_async<Q: AsyncUnitQuery + ?Sized>(
+    query: &Q,
+    definition: &str,
+    stack: &mut Vec<String>,
+) -> Resolved {
+    let (mut factor, unit) = parse_definition(definition).map_err(UnitError::unlocated)?;
+    let mut dimension = Dimension::default();
+    for partial in &unit.partials {
+        let (f, d) = resolve_in_async(query, &partial.name, stack).await?;
+        factor *= f.powf(partial.exp().to_f64());
+        dimension = dimension.dot(&d, partial.exp())?;
+    }
+    Ok((factor, dimension))
+}
+
+fn check_cycle(stack: &[String], unit_name: &str) -> Result<(), UnitError> {
+    match stack.iter().position(|name| name == unit_name) {
+        Some(start) => {
+            let mut chain = stack[start..].to_vec();
+            chain.push(unit_name.to_owned());
+            Err(UnitError::CyclicDefinition(chain))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Catalogue row disagreeing with its definition, reported by [`check_definitions`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum DefinitionIssue {
+    /// The definition does not resolve: syntax error, unknown unit or cycle.
+    Unresolved { unit: String, error: String },
+    Factor {
+        unit: String,
+        stored: f64,
+        defined: f64,
+    },
+    Dimension {
+        unit: String,
+        stored: Dimension,
+        defined: Dimension,
+    },
+}
+
+impl std::fmt::Display for DefinitionIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unresolved { unit, error } => write!(f, "{}: {}", unit, error),
+            Self::Factor {
+                unit,
+                stored,
+                defined,
+            } => write!(
+                f,
+                "{}: stored factor {} but its definition gives {}",
+                unit, stored, defined
+            ),
+            Self::Dimension {
+                unit,
+                stored,
+                defined,
+            } => write!(
+                f,
+                "{}: stored dimension {} but its definition gives {}",
+                unit, stored, defined
+            ),
+        }
+    }
+}
+
+/// Compares the stored factor and dimension of a defined unit with its definition. Rows
+/// declared only by their definition store neither (`NULL` factor and dimension).
+pub(crate) fn compare(
+    unit: &str,
+    stored_factor: f64,
+    stored_dimension: Option<Dimension>,
+    resolved: Resolved,
+) -> Option<DefinitionIssue> {
+    let unit = unit.to_owned();
+    let (defined, dimension) = match resolved {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            let error = e.to_string();
+            return Some(DefinitionIssue::Unresolved { unit, error });
+        }
+    };
+    if let Some(stored) = stored_dimension
+        && stored != dimension
+    {
+        return Some(DefinitionIssue::Dimension {
+            unit,
+            stored,
+            defined: dimension,
+        });
+    }
+    if stored_factor != 0. && ((stored_factor - defined) / defined).abs() > TOLERANCE {
+        return Some(DefinitionIssue::Factor {
+            unit,
+            stored: stored_factor,
+            defined,
+        });
+    }
+    None
+}
+
+/// Stored dimension of `unit`, `None` when its row has none.
+fn stored_dimension(
+    dimension: Result<(String, Dimension), UnitError>,
+) -> Result<Option<Dimension>, UnitError> {
+    match dimension {
+        Ok((_, dimension)) => Ok(Some(dimension)),
+        Err(UnitError::NoStoredDimension { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Resolves every defined unit of the catalogue and reports those whose stored factor or
+/// dimension disagree with their definition, or whose definition cannot be resolved.
+pub fn check_definitions<Q: UnitQuery + ?Sized>(
+    query: &Q,
+) -> Result<Vec<DefinitionIssue>, UnitError> {
+    let mut issues = Vec::new();
+    for name in query.get_unit_list()? {
+        let unit = ElementUnit::new(&name, 1);
+        if query.get_definition(&unit)?.is_none() {
+            continue;
+        }
+        let stored_factor = query.get_conversion_factor(&unit)?;
+        let stored_dimension = stored_dimension(query.get_dimension(&unit))?;
+        issues.extend(compare(
+            &name,
+            stored_factor,
+            stored_dimension,
+            resolve(query, &unit),
+        ));
+    }
+    Ok(issues)
+}
+
+/// See [`check_definitions`].
+pub async fn check_definitions_async<Q: AsyncUnitQuery + ?Sized>(
+    query: &Q,
+) -> Result<Vec<DefinitionIssue>, UnitError> {
+    let mut issues = Vec::new();
+    for name in query.get_unit_list().await? {
+        let unit = ElementUnit::new(&name, 1);
+        if query.get_definition(&unit).await?.is_none() {
+            continue;
+        }
+        let stored_factor = query.get_conversion_factor(&unit).await?;
+        let stored_dimension = stored_dimension(query.get_dimension(&unit).await)?;
+        issues.extend(compare(
+            &name,
+            stored_factor,
+            stored_dimension,
+            resolve_async(query, &unit).await,
+        ));
+    }
+    Ok(issues)
+}
+
+/// Whether a catalogue unit of factor `factor` and dimension `dimension` can stand for a unit
+/// of `target` factor and dimension: same scale, a physical quantity (dimensionless units such
+/// as `bit` would name any ratio) and no offset.
+fn names(factor: f64, dimension: Dimension, target: (f64, Dimension), offset: f64) -> bool {
+    dimension == target.1
+        && !dimension.is_dimensionless()
+        && ((factor - target.0) / target.0).abs() <= TOLERANCE
+        && offset == 0.
+}
+
+/// First catalogue unit with the factor and dimension `target`, filled, to replace a product of
+/// units (`J` for `kg*m^2*s^-2`). Derived units known only by their definition count (`W`).
+pub(crate) async fn named_unit_async<Q: AsyncUnitQuery + ?Sized>(
+    query: &Q,
+    target: (f64, Dimension),
+) -> Result<Option<ElementUnit>, UnitError> {
+    for name in query.get_unit_list().await? {
+        let mut unit = ElementUnit::new(&name, 1);
+        let Ok((factor, dimension)) = resolve_async(query, &unit).await else {
+            continue;
+        };
+        let offset = query.get_conversion_offset(&unit).await?;
+        if names(factor, dimension, target, offset) {
+            if let Ok(dim) = query.get_dimension_name(&unit).await {
+                unit.set_dim(&dim);
+            }
+            unit.set_factor(factor);
+            return Ok(Some(unit));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(all(test, feature = "embedded"))]
+mod test {
+    use super::*;
+    use crate::unitquery::MemoryUnitQuery;
+
+    #[test]
+    fn test_parse_definition() {
+        let (scale, unit) = parse_definition("1000*W*h").unwrap();
+        assert!(scale == 1000.);
+        assert!(unit.partials.len() == 2 && unit.partials[0].name == "W");
+
+        let (scale, unit) = parse_definition("kg*m/s^2").unwrap();
+        assert!(scale == 1.);
+        assert!(unit.partials[2].exp() == crate::Rational::from(-2));
+
+        let (scale, unit) = parse_definition("4.184 J").unwrap();
+        assert!(scale == 4.184 && unit.partials[0].name == "J");
+        let (scale, unit) = parse_definition("1e-2").unwrap();
+        assert!(scale == 0.01 && unit.partials.is_empty());
+        assert!(parse_definition("1x2*m").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let c = MemoryUnitQuery::embedded().await.unwrap();
+        let (factor, dimension) = resolve(&c, &ElementUnit::new("kWh", 1)).unwrap();
+        assert!((factor - 3.6e6).abs() < 1e-6);
+        assert!(dimension == Dimension::from([1, -2, 2, 0, 0, 0, 0]));
+
+        // Prefixed units defined only by their definition
+        let (factor, dimension) = resolve(&c, &ElementUnit::new("MW", 1)).unwrap();
+        assert!(factor == 1e6);
+        assert!(dimension == Dimension::from([1, -3, 2, 0, 0, 0, 0]));
+
+        let (factor, _) = resolve_async(&c, &ElementUnit::new("kcal", 1))
+            .await
+            .unwrap();
+        assert!((factor - 4184.).abs() < 1e-9);
+        assert!(resolve(&c, &ElementUnit::new("FALSEUNIT", 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shipped_definitions() {
+        let c = MemoryUnitQuery::embedded().await.unwrap();
+        let issues = check_definitions(&c).unwrap();
+        assert!(issues.is_empty(), "{:?}", issues);
+        assert!(check_definitions_async(&c).await.unwrap().is_empty());
+    }
+
+    /// Catalogue with definitions of its own over the shipped one.
+    struct Defined(MemoryUnitQuery, &'static [(&'static str, &'static str)]);
+
+    impl UnitQuery for Defined {
+        fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+            UnitQuery::get_conversion_factor(&self.0, unit)
+        }
+        fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+            UnitQuery::get_conversion_offset(&self.0, unit)
+        }
+        fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+            UnitQuery::get_unit_list(&self.0)
+        }
+        fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+            UnitQuery::get_dimension(&self.0, unit)
+        }
+        fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
+            UnitQuery::get_dimension_name(&self.0, p_unit)
+        }
+        fn accepts_prefix(
+            &self,
+            unit_name: &str,
+            kind: crate::PrefixKind,
+        ) -> Result<bool, UnitError> {
+            UnitQuery::accepts_prefix(&self.0, unit_name, kind)
+        }
+        fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+            match self.1.iter().find(|(name, _)| *name == unit.name) {
+                Some((_, definition)) => Ok(Some(definition.to_string())),
+                None => UnitQuery::get_definition(&self.0, unit),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cycle() {
+        let c = Defined(
+            MemoryUnitQuery::embedded().await.unwrap(),
+            &[("J", "N*m"), ("N", "J/m"), ("g", "2*kg")],
+        );
+        match resolve(&c, &ElementUnit::new("cal", 1)) {
+            Err(UnitError::CyclicDefinition(chain)) => assert!(chain == ["J", "N", "J"]),
+            _ => panic!("cycle not detected"),
+        }
+
+        let issues = check_definitions(&c).unwrap();
+        assert!(issues.contains(&DefinitionIssue::Factor {
+            unit: "g".to_owned(),
+            stored: 1e-3,
+            defined: 2.
+        }));
+        assert!(
+            issues
+                .iter()
+                .any(|i| matches!(i, DefinitionIssue::Unresolved { unit, .. } if unit == "kcal"))
+        );
+    }
+}
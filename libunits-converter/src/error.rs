@@ -1,26 +1,202 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::ops::Range;
+
 use thiserror::*;
 
-use crate::datatypes::Dimension;
+use crate::{Dimension, ElementUnit};
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum UnitError {
     #[error("Error in query: {0}")]
     Query(String),
 
-    #[error("Unit doesn´t exist : {0}")]
-    BadUnit(String),
+    /// `span` locates the unit in the parsed text when known.
+    #[error("Unit doesn´t exist : {name}{}", did_you_mean(.suggestions))]
+    BadUnit {
+        name: String,
+        span: Option<Range<usize>>,
+        suggestions: Vec<String>,
+    },
+
+    #[error("Ambiguous unit {name}, could be: {}", .candidates.join(", "))]
+    AmbiguousUnit {
+        name: String,
+        span: Option<Range<usize>>,
+        candidates: Vec<String>,
+    },
+
+    /// Units of the cycle, the first one repeated at the end.
+    #[error("Cyclic unit definition: {}", .0.join(" -> "))]
+    CyclicDefinition(Vec<String>),
+
+    /// Exponent beyond what a fraction of `i32` holds, as computed (`2147488281/1`).
+    #[error("Exponent out of range: {0}")]
+    Overflow(String),
+
+    /// Dimension named by a unit but missing from the dimension table.
+    #[error("No dimension {dimension}")]
+    NoDimension { dimension: String },
+
+    /// Unit known only by its definition, its row names no dimension.
+    #[error("{unit} has no stored dimension")]
+    NoStoredDimension { unit: String },
+
+    #[error("Dimensions mismatch: {0} and {1}")]
+    BadDimension(Dimension, Dimension),
 
-    // #[error("Dimensions mismtach {0} {0}")]
-    // BadDimension(Dimension, Dimension),
-    //
-    #[error("Dimensions mismtach")]
-    BadDimension,
+    #[error("Database not found, looked for: {0}")]
+    MissingDatabase(String),
 
-    #[error("Impossible to parse {0}")]
-    ParseError(String),
+    #[error("Invalid database {0}: {1}")]
+    BadDatabase(String, String),
+
+    /// `span` locates the faulty part of `input`.
+    #[error("Impossible to parse {input}: {message}")]
+    ParseError {
+        input: String,
+        message: String,
+        span: Option<Range<usize>>,
+    },
 
     #[error("{0}")]
     Custom(String),
 }
+
+fn did_you_mean(suggestions: &[String]) -> String {
+    match suggestions {
+        [] => String::new(),
+        _ => format!(", did you mean {}?", suggestions.join(", ")),
+    }
+}
+
+impl UnitError {
+    pub fn bad_unit(name: &str) -> Self {
+        Self::BadUnit {
+            name: name.to_owned(),
+            span: None,
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn parse(input: &str, message: impl Into<String>, span: Option<Range<usize>>) -> Self {
+        Self::ParseError {
+            input: input.to_owned(),
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Byte range of the offending part of the parsed text.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Self::BadUnit { span, .. }
+            | Self::AmbiguousUnit { span, .. }
+            | Self::ParseError { span, .. } => span.clone(),
+            _ => None,
+        }
+    }
+
+    /// Locates an error about `unit` at the place it was parsed from, errors about other units
+    /// (met while resolving a definition) are left as they are.
+    pub fn at(mut self, unit: &ElementUnit) -> Self {
+        if let Self::BadUnit { name, span, .. } | Self::AmbiguousUnit { name, span, .. } = &mut self
+            && *name == unit.name
+            && span.is_none()
+        {
+            *span = unit.span();
+        }
+        self
+    }
+
+    /// Whether this is the error of an unknown `unit`, rather than of a unit its definition uses.
+    pub(crate) fn is_unknown(&self, unit: &ElementUnit) -> bool {
+        matches!(self, Self::BadUnit { name, suggestions, .. } if *name == unit.name && suggestions.is_empty())
+    }
+
+    /// Sets the suggestions of an unknown unit error, other errors are left as they are.
+    pub fn with_suggestions(mut self, names: Vec<String>) -> Self {
+        if let Self::BadUnit { suggestions, .. } = &mut self {
+            *suggestions = names;
+        }
+        self
+    }
+
+    /// Drops the span of an error raised on another text than the one the caller parsed.
+    pub(crate) fn unlocated(mut self) -> Self {
+        if let Self::BadUnit { span, .. }
+        | Self::AmbiguousUnit { span, .. }
+        | Self::ParseError { span, .. } = &mut self
+        {
+            *span = None;
+        }
+        self
+    }
+
+    /// Moves the span of an error raised on a part of a text starting at byte `offset`, so that
+    /// it locates the error in the whole text.
+    pub(crate) fn shifted(mut self, offset: usize) -> Self {
+        if let Self::BadUnit { span, .. }
+        | Self::AmbiguousUnit { span, .. }
+        | Self::ParseError { span, .. } = &mut self
+            && let Some(span) = span
+        {
+            *span = span.start + offset..span.end + offset;
+        }
+        self
+    }
+
+    /// `input` split around the span of the error: before, offending part, after. `None` when
+    /// the error has no span or it does not fit in `input`.
+    pub fn highlight<'a>(&self, input: &'a str) -> Option<(&'a str, &'a str, &'a str)> {
+        let span = self.span()?;
+        let token = input.get(span.clone())?;
+        Some((&input[..span.start], token, &input[span.end..]))
+    }
+
+    /// Message followed by `input` with the offending part underlined, when known:
+    ///
+    /// ```text
+    /// Unit doesn´t exist : kgs, did you mean kg?
+    ///   kgs/h
+    ///   ^^^
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        match self.highlight(input) {
+            Some((before, token, _)) => format!(
+                "{}\n  {}\n  {}{}",
+                self,
+                input,
+                " ".repeat(before.chars().count()),
+                "^".repeat(token.chars().count().max(1))
+            ),
+            None => self.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut unit = ElementUnit::new("kgs", 1);
+        unit.set_span(Some(0..3));
+        let mut e = UnitError::bad_unit("kgs").at(&unit);
+        if let UnitError::BadUnit { suggestions, .. } = &mut e {
+            suggestions.push("kg".to_owned());
+        }
+        assert!(e.to_string() == "Unit doesn´t exist : kgs, did you mean kg?");
+        assert!(e.highlight("kgs/h") == Some(("", "kgs", "/h")));
+        assert!(e.render("kgs/h").ends_with("\n  kgs/h\n  ^^^"));
+
+        // Errors about another unit keep their place, spans out of the input are ignored
+        let e = UnitError::bad_unit("W").at(&unit);
+        assert!(e.span().is_none());
+        assert!(e.render("kgs") == e.to_string());
+        let e = UnitError::parse("m^", "expected exponent", Some(2..2));
+        assert!(e.render("m^").ends_with("\n  m^\n    ^"));
+        assert!(e.highlight("m").is_none());
+    }
+}
@@ -0,0 +1,649 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Calculator over values with units, `3 km / 20 min to m/s`.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::{
+    Rational, Unit, UnitError, UnitParser, Value,
+    aio::{AsyncUnitConverter, AsyncUnitFactory},
+};
+
+/// Signs read as the ASCII operator they stand for, as pasted from documents.
+const OPERATOR_SIGNS: [(char, char); 5] =
+    [('×', '*'), ('·', '*'), ('⋅', '*'), ('÷', '/'), ('−', '-')];
+
+/// Words introducing the unit of the result.
+const TARGET_KEYWORDS: [&str; 2] = ["to", "in"];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    /// `+ - * / ^ ( ) = ;`, other signs already mapped to one of them.
+    Operator(char),
+}
+
+fn operator(c: char) -> Option<char> {
+    match c {
+        '+' | '-' | '*' | '/' | '^' | '(' | ')' | '=' | ';' => Some(c),
+        _ => OPERATOR_SIGNS
+            .iter()
+            .find(|(sign, _)| *sign == c)
+            .map(|(_, op)| *op),
+    }
+}
+
+/// Length of the number at the start of `text`, exponent included (`1.5e-3`).
+fn number_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let mut len = digits(0);
+    if bytes.get(len) == Some(&b'.') {
+        len += 1 + digits(len + 1);
+    }
+    if matches!(bytes.get(len), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+        let exponent = digits(len + 1 + sign);
+        if exponent > 0 {
+            len += 1 + sign + exponent;
+        }
+    }
+    len
+}
+
+/// Tokens of `input` with their byte range. The exponent symbol and the delimiter of `parser`
+/// are read as `^` and `*` (`m**2` with `**` as exponent symbol, `kg.m` with `.` as delimiter).
+/// A name runs up to the next space, operator or symbol, so that any unit the parser reads fits
+/// in one (`m²`, `°C`, `ft'`).
+fn tokenize<P: UnitParser>(
+    input: &str,
+    parser: &P,
+) -> Result<Vec<(Token, Range<usize>)>, UnitError> {
+    // The longest first, `**` is not read as two `*`
+    let mut symbols = [
+        (parser.get_exp_symbol().as_str(), '^'),
+        (parser.get_delimiter().as_str(), '*'),
+    ];
+    symbols.sort_by_key(|(symbol, _)| std::cmp::Reverse(symbol.len()));
+    let symbol = |at: usize| {
+        symbols
+            .iter()
+            .find(|(symbol, _)| !symbol.is_empty() && input[at..].starts_with(symbol))
+    };
+
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        // `.5` is a number even with `.` as delimiter
+        let number = c.is_ascii_digit()
+            || (c == '.' && input[start + 1..].starts_with(|c: char| c.is_ascii_digit()));
+        if c.is_whitespace() {
+            chars.next();
+        } else if let Some((symbol, op)) = symbol(start).filter(|_| !number) {
+            tokens.push((Token::Operator(*op), start..start + symbol.len()));
+            while chars.peek().is_some_and(|(i, _)| *i < start + symbol.len()) {
+                chars.next();
+            }
+        } else if let Some(op) = operator(c) {
+            chars.next();
+            tokens.push((Token::Operator(op), start..start + c.len_utf8()));
+        } else if c.is_ascii_digit() || c == '.' {
+            let len = number_len(&input[start..]);
+            let text = &input[start..start + len];
+            // `1.2.3` is not `1.2` times `.3`
+            let rest = &input[start + len..];
+            let glued = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = match text.parse() {
+                Ok(number) if glued == 0 => number,
+                _ => {
+                    let span = start..start + (len + glued).max(1);
+                    return Err(UnitError::parse(input, "bad number", Some(span)));
+                }
+            };
+            tokens.push((Token::Number(number), start..start + len));
+            while chars.peek().is_some_and(|(i, _)| *i < start + len) {
+                chars.next();
+            }
+        } else {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_whitespace() || operator(c).is_some() || symbol(i).is_some() {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push((Token::Name(input[start..end].to_owned()), start..end));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Step of an expression in postfix order.
+#[derive(Clone, Debug, PartialEq)]
+enum Op {
+    Number(f64),
+    /// Variable or unit, as written with its byte range.
+    Name(String, Range<usize>),
+    Neg,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow(Rational),
+}
+
+/// Statement of the calculator, `name = expr to unit` with both ends optional.
+#[derive(Debug, PartialEq)]
+struct Statement {
+    variable: Option<String>,
+    ops: Vec<Op>,
+    /// Unit of the result as written, with its byte range.
+    target: Option<(String, Range<usize>)>,
+}
+
+/// Recursive-descent parser over the expression grammar:
+///
+/// ```text
+/// statement := (name '=')? expr (('to' | 'in') unit)?
+/// expr      := term (('+' | '-') term)*
+/// term      := product (('*' | '/') product)*
+/// product   := '-' product | power power*
+/// power     := atom ('^' exponent)?
+/// atom      := number | name | '(' expr ')'
+/// exponent  := '-'? number | '(' '-'? number ('/' number)? ')'
+/// ```
+///
+/// Juxtaposition binds tighter than `*` and `/` so that `3 km / 20 min` divides by `20 min`. The
+/// unit after `to` or `in` is everything up to the end, read by the unit parser (`in` alone is
+/// an inch: `12 in to cm`).
+struct StatementParser<'a> {
+    input: &'a str,
+    tokens: &'a [(Token, Range<usize>)],
+    /// Start of the expression, where an empty one is reported.
+    start: usize,
+    pos: usize,
+    ops: Vec<Op>,
+}
+
+impl<'a> StatementParser<'a> {
+    fn parse(input: &'a str, tokens: &'a [(Token, Range<usize>)]) -> Result<Statement, UnitError> {
+        let (variable, tokens, start) = match tokens {
+            [
+                (Token::Name(name), _),
+                (Token::Operator('='), span),
+                rest @ ..,
+            ] => (Some(name.clone()), rest, span.end),
+            _ => (
+                None,
+                tokens,
+                tokens.first().map_or(input.len(), |(_, span)| span.start),
+            ),
+        };
+        let (tokens, target) = match Self::target(tokens) {
+            Some(i) => {
+                let range = tokens[i + 1].1.start..tokens.last().unwrap().1.end;
+                let text = input[range.clone()].to_owned();
+                (&tokens[..i], Some((text, range)))
+            }
+            None => (tokens, None),
+        };
+        let mut parser = StatementParser {
+            input,
+            tokens,
+            start,
+            pos: 0,
+            ops: Vec::new(),
+        };
+        parser.expr()?;
+        if parser.pos < tokens.len() {
+            return Err(parser.error("operator"));
+        }
+        Ok(Statement {
+            variable,
+            ops: parser.ops,
+            target,
+        })
+    }
+
+    /// Position of the last `to` or `in` outside parentheses followed by a unit.
+    fn target(tokens: &[(Token, Range<usize>)]) -> Option<usize> {
+        let mut depth = 0i32;
+        let mut found = None;
+        for (i, (token, _)) in tokens.iter().enumerate() {
+            match token {
+                Token::Operator('(') => depth += 1,
+                Token::Operator(')') => depth -= 1,
+                Token::Name(name)
+                    if depth == 0
+                        && i > 0
+                        && i + 1 < tokens.len()
+                        && TARGET_KEYWORDS.contains(&name.as_str()) =>
+                {
+                    found = Some(i)
+                }
+                _ => {}
+            }
+        }
+        found
+    }
+
+    fn error(&self, expected: &str) -> UnitError {
+        match self.tokens.get(self.pos) {
+            Some((_, span)) => UnitError::parse(
+                self.input,
+                format!(
+                    "expected {} but found '{}'",
+                    expected,
+                    &self.input[span.clone()]
+                ),
+                Some(span.clone()),
+            ),
+            None => {
+                let end = self.tokens.last().map_or(self.start, |(_, span)| span.end);
+                UnitError::parse(
+                    self.input,
+                    format!("expected {} but reached end of input", expected),
+                    Some(end..end),
+                )
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn eat(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Operator(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<(), UnitError> {
+        self.term()?;
+        loop {
+            if self.eat('+') {
+                self.term()?;
+                self.ops.push(Op::Add);
+            } else if self.eat('-') {
+                self.term()?;
+                self.ops.push(Op::Sub);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<(), UnitError> {
+        self.product()?;
+        loop {
+            if self.eat('*') {
+                self.product()?;
+                self.ops.push(Op::Mul);
+            } else if self.eat('/') {
+                self.product()?;
+                self.ops.push(Op::Div);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<(), UnitError> {
+        if self.eat('-') {
+            self.product()?;
+            self.ops.push(Op::Neg);
+            return Ok(());
+        }
+        self.power()?;
+        while matches!(
+            self.peek(),
+            Some(Token::Number(_) | Token::Name(_) | Token::Operator('('))
+        ) {
+            self.power()?;
+            self.ops.push(Op::Mul);
+        }
+        Ok(())
+    }
+
+    fn power(&mut self) -> Result<(), UnitError> {
+        self.atom()?;
+        if self.eat('^') {
+            let exp = self.exponent()?;
+            self.ops.push(Op::Pow(exp));
+        }
+        Ok(())
+    }
+
+    fn atom(&mut self) -> Result<(), UnitError> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Number(number), _)) => {
+                self.pos += 1;
+                self.ops.push(Op::Number(*number));
+            }
+            Some((Token::Name(name), span)) => {
+                self.pos += 1;
+                self.ops.push(Op::Name(name.clone(), span.clone()));
+            }
+            Some((Token::Operator('('), _)) => {
+                self.pos += 1;
+                self.expr()?;
+                if !self.eat(')') {
+                    return Err(self.error("')'"));
+                }
+            }
+            _ => return Err(self.error("number, unit or '('")),
+        }
+        Ok(())
+    }
+
+    fn exponent(&mut self) -> Result<Rational, UnitError> {
+        if !self.eat('(') {
+            return self.signed_integer();
+        }
+        let mut exp = self.signed_integer()?;
+        if self.eat('/') {
+            let start = self.pos;
+            let denominator = self.signed_integer()?;
+            if denominator.is_zero() {
+                self.pos = start;
+                return Err(self.error("non null denominator"));
+            }
+            exp = exp.checked_div(denominator)?;
+        }
+        if !self.eat(')') {
+            return Err(self.error("')'"));
+        }
+        Ok(exp)
+    }
+
+    fn signed_integer(&mut self) -> Result<Rational, UnitError> {
+        let negative = self.eat('-');
+        match self.peek() {
+            Some(Token::Number(n)) if n.fract() == 0. && n.abs() <= i32::MAX as f64 => {
+                self.pos += 1;
+                let n = Rational::from(*n as i32);
+                Ok(if negative { -n } else { n })
+            }
+            _ => Err(self.error("integer exponent")),
+        }
+    }
+}
+
+/// Calculator keeping variables between the statements it evaluates.
+///
+/// Names are looked up among the variables first, then read as units with the parser given to
+/// [`Evaluator::eval`]. Sums convert their right operand to the unit of the left one, results
+/// without a target unit are [simplified](AsyncUnitConverter::simplify_value) (`5 bar * 2 L` is
+/// `1000 J`).
+#[derive(Default)]
+pub struct Evaluator {
+    variables: HashMap<String, Value>,
+}
+
+impl Evaluator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn variables(&self) -> &HashMap<String, Value> {
+        &self.variables
+    }
+
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_owned(), value);
+    }
+
+    /// Evaluates the statements of `input`, separated by `;`, and returns the value of the last
+    /// one. Error spans are byte ranges of `input`.
+    pub async fn eval<P, F, C>(
+        &mut self,
+        parser: &P,
+        factory: &F,
+        converter: &C,
+        input: &str,
+    ) -> Result<Value, UnitError>
+    where
+        P: UnitParser,
+        F: AsyncUnitFactory,
+        C: AsyncUnitConverter,
+    {
+        let tokens = tokenize(input, parser)?;
+        let mut result = None;
+        for statement in tokens.split(|(token, _)| *token == Token::Operator(';')) {
+            if statement.is_empty() {
+                continue;
+            }
+            let statement = StatementParser::parse(input, statement)?;
+            let mut value = self.run(parser, factory, converter, &statement.ops).await?;
+            value = match &statement.target {
+                Some((text, span)) => {
+                    let unit = factory
+                        .parse_fill(parser, text)
+                        .await
+                        .map_err(|e| e.shifted(span.start))?;
+                    converter.convert(&value, &unit).await?
+                }
+                None => converter.simplify_value(&value).await?,
+            };
+            if let Some(name) = statement.variable {
+                self.variables.insert(name, value.clone());
+            }
+            result = Some(value);
+        }
+        result.ok_or_else(|| UnitError::parse(input, "nothing to evaluate", Some(0..input.len())))
+    }
+
+    async fn run<P, F, C>(
+        &self,
+        parser: &P,
+        factory: &F,
+        converter: &C,
+        ops: &[Op],
+    ) -> Result<Value, UnitError>
+    where
+        P: UnitParser,
+        F: AsyncUnitFactory,
+        C: AsyncUnitConverter,
+    {
+        let mut stack: Vec<Value> = Vec::new();
+        for op in ops {
+            let value = match op {
+                Op::Number(number) => Value::from_value(Unit::from_vec(Vec::new()), *number),
+                Op::Name(name, span) => match self.variables.get(name) {
+                    Some(value) => value.clone(),
+                    None => {
+                        let unit = factory
+                            .parse_fill(parser, name)
+                            .await
+                            .map_err(|e| e.shifted(span.start))?;
+                        Value::from_value(unit, 1.)
+                    }
+                },
+                Op::Neg => -stack.pop().unwrap(),
+                Op::Pow(exp) => stack.pop().unwrap().powf(*exp)?,
+                Op::Add | Op::Sub | Op::Mul | Op::Div => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    match op {
+                        Op::Add => converter.add(&lhs, &rhs).await?,
+                        Op::Sub => converter.sub(&lhs, &rhs).await?,
+                        Op::Mul => (lhs * rhs)?,
+                        _ => (lhs / rhs)?,
+                    }
+                }
+            };
+            stack.push(value);
+        }
+        Ok(stack.pop().unwrap())
+    }
+}
+
+#[cfg(all(test, feature = "embedded"))]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{InlineUnitParser, MainConverter, MainUnitFactory, unitquery::MemoryUnitQuery};
+
+    fn parse(input: &str) -> Result<Statement, UnitError> {
+        StatementParser::parse(input, &tokenize(input, &InlineUnitParser::default())?)
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let parser = InlineUnitParser::default();
+        let tokens: Vec<Token> = tokenize("2.5e-3 kg·m²/(s − °C) ft'", &parser)
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+        let name = |name: &str| Token::Name(name.to_owned());
+        assert!(
+            tokens
+                == [
+                    Token::Number(2.5e-3),
+                    name("kg"),
+                    Token::Operator('*'),
+                    name("m²"),
+                    Token::Operator('/'),
+                    Token::Operator('('),
+                    name("s"),
+                    Token::Operator('-'),
+                    name("°C"),
+                    Token::Operator(')'),
+                    name("ft'"),
+                ]
+        );
+        assert!(tokenize("1e", &parser).unwrap()[1].0 == name("e"));
+        for input in ["1.2.3", "2.5e3.1", "."] {
+            let e = tokenize(input, &parser).err().unwrap();
+            assert!(e.span() == Some(0..input.len()), "{}", input);
+        }
+
+        // Symbols of the unit parser
+        let mut parser = InlineUnitParser::default();
+        parser.set_exp_symbol("**");
+        parser.set_delimiter(".");
+        let tokens: Vec<Token> = tokenize("kg.m**2 .5", &parser)
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+        let expected = [
+            name("kg"),
+            Token::Operator('*'),
+            name("m"),
+            Token::Operator('^'),
+            Token::Number(2.),
+            Token::Number(0.5),
+        ];
+        assert!(tokens == expected);
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        let name = |name: &str, span: Range<usize>| Op::Name(name.to_owned(), span);
+        let statement = parse("3 km / 20 min to m/s").unwrap();
+        assert!(
+            statement.ops
+                == [
+                    Op::Number(3.),
+                    name("km", 2..4),
+                    Op::Mul,
+                    Op::Number(20.),
+                    name("min", 10..13),
+                    Op::Mul,
+                    Op::Div,
+                ]
+        );
+        assert!(statement.target == Some(("m/s".to_owned(), 17..20)));
+
+        let statement = parse("x = -2^2 + 1").unwrap();
+        assert!(statement.variable == Some("x".to_owned()));
+        let expected = [
+            Op::Number(2.),
+            Op::Pow(Rational::from(2)),
+            Op::Neg,
+            Op::Number(1.),
+            Op::Add,
+        ];
+        assert!(statement.ops == expected);
+
+        // `in` is an inch unless a unit follows it
+        assert!(parse("12 in to cm").unwrap().target.unwrap().0 == "cm");
+        assert!(parse("5 ft to in").unwrap().target.unwrap().0 == "in");
+        assert!(parse("12 in").unwrap().target.is_none());
+        let statement = parse("V^(1/2) in (V)").unwrap();
+        assert!(statement.ops[1] == Op::Pow(Rational::new(1, 2)));
+
+        let e = parse("3 km /").err().unwrap();
+        assert!(e.span() == Some(6..6));
+        let e = parse("x =").err().unwrap();
+        assert!(e.span() == Some(3..3));
+        assert!(parse("(3 km").is_err());
+        assert!(parse("m^(1/0)").is_err());
+        assert!(parse("m^1.5").is_err());
+        assert!(parse("3 ) km").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_eval() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let factory = MainUnitFactory::new(c.clone());
+        let converter = MainConverter::new(c);
+        let parser = InlineUnitParser::default();
+        let mut evaluator = Evaluator::new();
+        let mut eval = async |input: &str| {
+            evaluator
+                .eval(&parser, &factory, &converter, input)
+                .await
+                .map(|value| format!("{:.6}", value))
+        };
+
+        assert!(eval("3 km / 20 min to m/s").await.unwrap() == "2.500000 m*s^-1");
+        assert!(eval("5 bar * 2 L").await.unwrap() == "1000.000000 J");
+        assert!(eval("1 m + 20 cm").await.unwrap() == "1.200000 m");
+        assert!(eval("(2 + 3) * 2 ^ 2").await.unwrap() == "20.000000");
+        assert!(eval("20 degC + 5 K to degF").await.unwrap() == "77.000000 degF");
+        assert!(eval("(4 m^2)^(1/2) in cm").await.unwrap() == "200.000000 cm");
+        assert!(eval("12 in to cm").await.unwrap() == "30.480000 cm");
+
+        // Variables live from one statement, and one call, to the next
+        assert!(eval("d = 3 km; t = 20 min").await.unwrap() == "20.000000 min");
+        assert!(eval("d / t to km/h").await.unwrap() == "9.000000 km*h^-1");
+
+        let e = eval("1 m + 2 s").await.err().unwrap();
+        assert!(matches!(e, UnitError::BadDimension(..)));
+        let e = eval("3 kgs * 2").await.err().unwrap();
+        assert!(e.span() == Some(2..5));
+        assert!(e.to_string().ends_with("did you mean kg?"));
+        let e = eval("3 m to kgg").await.err().unwrap();
+        assert!(e.span() == Some(7..10));
+        assert!(eval(" ; ").await.is_err());
+        let e = eval("(m^(46341))^(46341)").await.err().unwrap();
+        assert!(matches!(e, UnitError::Overflow(_)));
+
+        // Symbols of the unit parser, in expressions and in the unit of the result
+        let mut parser = InlineUnitParser::default();
+        parser.set_exp_symbol("**");
+        let value = evaluator
+            .eval(&parser, &factory, &converter, "(2 m)**2 to cm**2")
+            .await
+            .unwrap();
+        assert!(value.to_string() == "40000 cm^2");
+    }
+}
@@ -1,64 +1,77 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::{ElementUnit, Unit, UnitError, UnitParser, unitquery::UnitQuery};
+use futures::executor::block_on;
+
+use crate::aio::{self, AsyncUnitQuery};
+use crate::{ElementUnit, Rational, Unit, UnitError, UnitParser};
 
 pub trait UnitFactory {
-    fn construct_unit(&self, name: &str, exp: f64) -> Result<ElementUnit, UnitError>;
+    fn construct_unit(
+        &self,
+        name: &str,
+        exp: impl Into<Rational>,
+    ) -> Result<ElementUnit, UnitError>;
     fn fill(&self, unit: &mut ElementUnit) -> Result<(), UnitError>;
     fn parse_fill<T: UnitParser>(&self, parser: &T, text: &str) -> Result<Unit, UnitError>;
 }
 
-pub struct MainUnitFactory<T: UnitQuery> {
-    query: Rc<T>,
+pub struct MainUnitFactory<T> {
+    pub(crate) query: Arc<T>,
 }
 
-impl<T: UnitQuery> MainUnitFactory<T> {
-    pub fn new(query: Rc<T>) -> Self {
+impl<T> MainUnitFactory<T> {
+    pub fn new(query: Arc<T>) -> Self {
         Self { query }
     }
 }
 
-impl<T: UnitQuery> UnitFactory for MainUnitFactory<T> {
-    fn construct_unit(&self, name: &str, exp: f64) -> Result<ElementUnit, UnitError> {
-        let mut unit = ElementUnit::new(name, exp);
-        self.fill(&mut unit)?;
-        Ok(unit)
+/// Blocking facade over [`AsyncUnitFactory`](aio::AsyncUnitFactory), must not be called from inside an async runtime.
+impl<T: AsyncUnitQuery> UnitFactory for MainUnitFactory<T> {
+    fn construct_unit(
+        &self,
+        name: &str,
+        exp: impl Into<Rational>,
+    ) -> Result<ElementUnit, UnitError> {
+        block_on(aio::AsyncUnitFactory::construct_unit(self, name, exp))
     }
 
     fn fill(&self, unit: &mut ElementUnit) -> Result<(), UnitError> {
-        unit.set_dim(&self.query.get_dimension_name(unit)?);
-        unit.set_factor(self.query.get_conversion_factor(unit)?);
-        Ok(())
+        block_on(aio::AsyncUnitFactory::fill(self, unit))
     }
-    fn parse_fill<G: UnitParser>(&self, parser: &G, text: &str) -> Result<Unit, UnitError> {
-        let mut unit = parser.parse_unit(text)?;
-
-        for pu in unit.partials.iter_mut() {
-            self.fill(pu)?;
-        }
 
-        Ok(unit)
+    fn parse_fill<G: UnitParser>(&self, parser: &G, text: &str) -> Result<Unit, UnitError> {
+        block_on(aio::AsyncUnitFactory::parse_fill(self, parser, text))
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "embedded"))]
 mod test {
-    use crate::unitquery::SqlUnitQuery;
+    use crate::unitquery::MemoryUnitQuery;
 
     use super::*;
     #[tokio::test]
     async fn test_construct() {
-        let c = Rc::new(SqlUnitQuery::new().await.unwrap());
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
         let converter = MainUnitFactory::new(c);
-        let pu = converter.construct_unit("g", 1.).unwrap();
-        let pu2 = converter.construct_unit("kg", 1.).unwrap();
-        let pu3 = converter.construct_unit("m", 1.).unwrap();
+        let pu = converter.construct_unit("g", 1).unwrap();
+        let pu2 = converter.construct_unit("kg", 1).unwrap();
+        let pu3 = converter.construct_unit("m", 1).unwrap();
 
         assert!(pu.dim == Some("mass".to_owned()));
         assert!(pu2.dim == Some("mass".to_owned()));
         assert!(pu3.dim == Some("length".to_owned()));
         assert!(pu.get_factor() == 1e-3);
+
+        let pu = converter.construct_unit("degC", 1).unwrap();
+        assert!(pu.dim == Some("temperature".to_owned()));
+        assert!(pu.get_offset() == 273.15);
+
+        let pu = converter.construct_unit("kWh", 1).unwrap();
+        assert!(pu.dim.is_none());
+        assert!((pu.get_factor() - 3.6e6).abs() < 1e-6);
+        let pu = converter.construct_unit("N", 1).unwrap();
+        assert!(pu.dim == Some("force".to_owned()));
     }
 }
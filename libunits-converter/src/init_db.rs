@@ -1,63 +1,128 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-pub(crate) async fn init_from_scratch(db_path: &str, migrations_path: &str) -> turso::Result<()> {
-    let filename = format!("{}/sqlite.db", db_path);
-    if !std::fs::exists(&filename).unwrap() {
-        println!("Prepare databse");
-        let db = turso::Builder::new_local(&filename).build().await?;
-
-        let conn = db.connect()?;
-        let migrations_list = [
-            format!("{}/002_dimensions.sql", migrations_path),
-            format!("{}/003_conversion.sql", migrations_path),
-        ];
-        for migration in migrations_list {
-            let sql_content = std::fs::read_to_string(migration).unwrap();
-            let queries: Vec<&str> = sql_content
-                .split(';')
-                .map(|s| s.trim())
-                .filter(|s| !s.is_empty())
-                .collect();
-
-            for query in queries {
-                conn.execute(query, ()).await?;
-                println!("Executed : {}", query);
-            }
+use std::path::Path;
+
+use libunits_converter::UnitError;
+use libunits_converter::migrations::{
+    Migration, apply, baseline, current_version, discover, legacy_version, pending,
+};
+
+const USAGE: &str = "Usage: init_db [--dry-run | status]";
+
+enum Mode {
+    /// Applies the pending migrations.
+    Migrate,
+    /// Lists the migrations which would be applied, the database is left untouched.
+    DryRun,
+    /// Prints the version of the database and its pending migrations.
+    Status,
+}
+
+/// Version of the database behind `conn`, a catalogue populated before versions were recorded
+/// is baselined at the version it reached unless `read_only`.
+async fn version(
+    conn: &turso::Connection,
+    migrations: &[Migration],
+    read_only: bool,
+) -> Result<u32, UnitError> {
+    match legacy_version(conn).await? {
+        Some(version) if read_only => Ok(version),
+        Some(version) => {
+            println!("Existing catalogue at version {}, recording it", version);
+            baseline(conn, migrations, version).await?;
+            Ok(version)
         }
+        None => current_version(conn).await,
+    }
+}
+
+pub(crate) async fn init_db(
+    filename: &Path,
+    migrations_path: &Path,
+    mode: Mode,
+) -> Result<(), UnitError> {
+    let migrations = discover(migrations_path)?;
+    let read_only = !matches!(mode, Mode::Migrate);
+
+    // Opening creates the file, which a read-only run must not do
+    let conn = if read_only && !filename.exists() {
+        None
     } else {
-        eprintln!("Database already set");
+        let open_error =
+            |e: turso::Error| UnitError::BadDatabase(filename.display().to_string(), e.to_string());
+        let db = turso::Builder::new_local(&filename.to_string_lossy())
+            .build()
+            .await
+            .map_err(open_error)?;
+        Some((db.connect().map_err(open_error)?, db))
+    };
+    let version = match &conn {
+        Some((conn, _)) => version(conn, &migrations, read_only).await?,
+        None => 0,
+    };
+    let pending = pending(&migrations, version);
+
+    match mode {
+        Mode::Status => {
+            println!("{} at version {}", filename.display(), version);
+            if pending.is_empty() {
+                println!("Up to date");
+            }
+            for migration in &pending {
+                println!("Pending : {}", migration.name);
+            }
+        }
+        Mode::DryRun => {
+            for migration in &pending {
+                println!("Would apply : {}", migration.name);
+            }
+        }
+        Mode::Migrate => {
+            let Some((conn, _)) = &conn else {
+                unreachable!("the database is opened to migrate")
+            };
+            for migration in &pending {
+                apply(conn, migration).await?;
+                println!("Applied : {}", migration.name);
+            }
+        }
     }
-    let mut perms = std::fs::metadata(&filename).unwrap().permissions();
-    perms.set_readonly(true);
-    // std::fs::set_permissions(filename, perms).unwrap();
     Ok(())
 }
 
 #[tokio::main]
 async fn main() {
+    let mode = match std::env::args().nth(1).as_deref() {
+        None => Mode::Migrate,
+        Some("--dry-run") => Mode::DryRun,
+        Some("status") => Mode::Status,
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
     let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
     let root = std::path::Path::new(&manifest_dir)
         .parent()
         .unwrap()
-        .to_path_buf()
-        .to_str()
-        .unwrap()
-        .to_owned();
+        .to_path_buf();
 
     let db_path = match std::env::var("DB_FILE_PATH") {
-        Ok(path) => path,
+        Ok(path) => std::path::PathBuf::from(path),
         Err(std::env::VarError::NotPresent) => {
-            let path = format!("{}/data", root);
             eprintln!("Using default database");
-            path
+            root.join("data")
         }
         Err(e) => panic!("{}", e),
     };
-    std::fs::create_dir_all(&db_path).unwrap();
-
-    let migrations_path = format!("{}/migrations/", root);
-
-    init_from_scratch(&db_path, &migrations_path).await.unwrap();
+    if matches!(mode, Mode::Migrate) {
+        std::fs::create_dir_all(&db_path).unwrap();
+    }
 
-    println!("Created databse at : {}", db_path);
+    let filename = db_path.join("sqlite.db");
+    if let Err(e) = init_db(&filename, &root.join("migrations"), mode).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
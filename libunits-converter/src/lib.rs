@@ -1,8 +1,15 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod aio;
+mod check;
 mod datatypes;
+mod definition;
 mod error;
+mod eval;
 mod parser;
+mod prefix;
+#[cfg(feature = "typed")]
+pub mod typed;
 pub mod unitquery;
 
 pub enum UnitMatch {
@@ -11,172 +18,268 @@ pub enum UnitMatch {
     Equal,
 }
 mod factory;
+pub mod migrations;
 
-use datatypes::Dimension;
 pub use factory::{MainUnitFactory, UnitFactory};
 
-pub use datatypes::{ElementUnit, Unit, Value};
+use aio::AsyncUnitQuery;
+pub use check::Diagnostic;
+pub use datatypes::{Dimension, ElementUnit, Rational, Unit, UnitStyle, Value};
+pub use definition::{DefinitionIssue, check_definitions, parse_definition};
 pub use error::UnitError;
+pub use eval::Evaluator;
+use futures::executor::block_on;
 pub use parser::{InlineUnitParser, UnitParser};
-use std::rc::Rc;
-use unitquery::{SqlUnitQuery, UnitQuery};
+pub use prefix::{PREFIXES, Prefix, PrefixKind};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "embedded")]
+use unitquery::MemoryUnitQuery;
+use unitquery::OverlayUnitQuery;
 
 pub trait UnitConverter {
-    fn is_valid_unit(&mut self, unit: &Unit) -> bool;
-    fn are_same_dimension(&self, unit1: &Unit, unit2: &Unit) -> (bool, Dimension, Dimension);
-    fn get_dimension(&self, unit: &Unit) -> Dimension;
-    fn get_dimension_mut(&self, unit: &mut Unit) -> Dimension;
+    fn is_valid_unit(&self, unit: &Unit) -> Result<bool, UnitError>;
+    fn are_same_dimension(
+        &self,
+        unit1: &Unit,
+        unit2: &Unit,
+    ) -> Result<(bool, Dimension, Dimension), UnitError>;
+    fn get_dimension(&self, unit: &Unit) -> Result<Dimension, UnitError>;
+    fn get_dimension_mut(&self, unit: &mut Unit) -> Result<Dimension, UnitError>;
     fn convert(&self, unit1: &Value, unit2: &Unit) -> Result<Value, UnitError>;
+    /// [`UnitConverter::convert`], `unit1` being replaced by the converted value.
     fn convert_mut(&self, unit1: &mut Value, unit2: &mut Unit) -> Result<Value, UnitError>;
     fn get_conversion_factor(&self, unit: &Unit) -> Result<f64, UnitError>;
+    fn get_conversion_offset(&self, unit: &Unit) -> Result<f64, UnitError>;
+    /// [`Unit::simplify`], then a product of units replaced by the catalogue unit of the same
+    /// factor and dimension when there is one (`kg*m^2*s^-2` is `J`, `J/s` is `W`).
+    fn simplify(&self, unit: &Unit) -> Result<Unit, UnitError>;
+    /// Value converted to the catalogue unit of its dimension that SI values are counted in when
+    /// its unit is a product (`10 L*bar` is `1000 J`), else [`UnitConverter::simplify`] of its
+    /// unit.
+    fn simplify_value(&self, value: &Value) -> Result<Value, UnitError>;
+    /// Sum in the unit of `lhs`, `rhs` being converted as a difference: no offset is applied,
+    /// `20 degC + 1 K` is `21 degC`. Fails with [`UnitError::BadDimension`] when the units
+    /// measure different quantities.
+    fn add(&self, lhs: &Value, rhs: &Value) -> Result<Value, UnitError>;
+    /// See [`UnitConverter::add`].
+    fn sub(&self, lhs: &Value, rhs: &Value) -> Result<Value, UnitError>;
 }
 
-pub struct MainConverter<T: UnitQuery> {
-    query: Rc<T>,
-    ulist: Option<Vec<String>>,
+/// Converter over a shared [`AsyncUnitQuery`], `Send + Sync` as soon as the query is, so a
+/// single instance can serve several threads or tasks.
+pub struct MainConverter<T> {
+    pub(crate) query: Arc<T>,
+    /// Catalogue unit names, with the [revision](AsyncUnitQuery::revision) they were read at.
+    ulist: RwLock<Option<(u64, Vec<String>)>>,
 }
 
-impl<T: UnitQuery> MainConverter<T> {
-    pub fn new(query: Rc<T>) -> Self {
-        Self { query, ulist: None }
-    }
-    fn fold_dimension<'a, I, P, F>(&self, partials: I, mut on_partial: F) -> Dimension
-    where
-        I: IntoIterator<Item = P>,
-        P: 'a + std::borrow::Borrow<ElementUnit>,
-        F: FnMut(P, &str),
-    {
-        let mut dimension = Dimension::default();
-
-        for partial in partials {
-            let partial_ref: &ElementUnit = partial.borrow();
-            let (name, dim) = self.query.get_dimension(partial_ref).unwrap();
-            dimension = dimension.dot(&dim, partial_ref.exp());
-            on_partial(partial, &name);
+impl<T> MainConverter<T> {
+    pub fn new(query: Arc<T>) -> Self {
+        Self {
+            query,
+            ulist: RwLock::new(None),
         }
+    }
 
-        dimension
+    /// Catalogue the converter looks units up in.
+    pub fn query(&self) -> &T {
+        &self.query
     }
 }
 
-//
-
-impl<T: UnitQuery> UnitConverter for MainConverter<T> {
-    fn is_valid_unit(&mut self, unit: &Unit) -> bool {
-        if self.ulist.is_none() {
-            self.ulist = Some(self.query.get_unit_list().unwrap()); //Do not use ok() to keep result check with unwrap
+impl<T: AsyncUnitQuery> MainConverter<T> {
+    /// Whether `name` is a catalogue unit, the list being read again once the query revision
+    /// moves (a unit defined or removed by the user).
+    async fn is_listed(&self, name: &str) -> Result<bool, UnitError> {
+        let revision = self.query.revision();
+        if let Some((seen, ulist)) = &*self.ulist.read().unwrap()
+            && *seen == revision
+        {
+            return Ok(ulist.iter().any(|u| u == name));
         }
-        unit.partials
-            .iter()
-            .all(|p_u| self.ulist.as_ref().unwrap().contains(&p_u.name))
+        let ulist = self.query.get_unit_list().await?;
+        let listed = ulist.iter().any(|u| u == name);
+        *self.ulist.write().unwrap() = Some((revision, ulist));
+        Ok(listed)
+    }
+}
+
+/// Blocking facade over [`AsyncUnitConverter`](aio::AsyncUnitConverter), must not be called from inside an async runtime.
+impl<T: AsyncUnitQuery> UnitConverter for MainConverter<T> {
+    fn is_valid_unit(&self, unit: &Unit) -> Result<bool, UnitError> {
+        block_on(aio::AsyncUnitConverter::is_valid_unit(self, unit))
     }
 
     fn get_conversion_factor(&self, unit: &Unit) -> Result<f64, UnitError> {
-        let mut cf = 1.;
-        for partial in &unit.partials {
-            let c = self.query.get_conversion_factor(partial).unwrap();
+        block_on(aio::AsyncUnitConverter::get_conversion_factor(self, unit))
+    }
 
-            cf *= c.powf(partial.exp());
-        }
-        Ok(cf)
+    fn get_conversion_offset(&self, unit: &Unit) -> Result<f64, UnitError> {
+        block_on(aio::AsyncUnitConverter::get_conversion_offset(self, unit))
     }
 
-    fn get_dimension(&self, unit: &Unit) -> Dimension {
-        self.fold_dimension(unit.partials.iter(), |_, _| {})
+    fn get_dimension(&self, unit: &Unit) -> Result<Dimension, UnitError> {
+        block_on(aio::AsyncUnitConverter::get_dimension(self, unit))
     }
 
-    fn get_dimension_mut(&self, unit: &mut Unit) -> Dimension {
-        self.fold_dimension(unit.partials.iter_mut(), |p, name| {
-            p.dim = Some(name.to_string());
-        })
+    fn get_dimension_mut(&self, unit: &mut Unit) -> Result<Dimension, UnitError> {
+        block_on(aio::AsyncUnitConverter::get_dimension_mut(self, unit))
     }
 
-    fn are_same_dimension(&self, unit1: &Unit, unit2: &Unit) -> (bool, Dimension, Dimension) {
-        let d1 = self.get_dimension(unit1);
-        let d2 = self.get_dimension(unit2);
-        (d1 == d2, d1, d2)
+    fn are_same_dimension(
+        &self,
+        unit1: &Unit,
+        unit2: &Unit,
+    ) -> Result<(bool, Dimension, Dimension), UnitError> {
+        block_on(aio::AsyncUnitConverter::are_same_dimension(
+            self, unit1, unit2,
+        ))
     }
 
     fn convert(&self, val: &Value, unit: &Unit) -> Result<Value, UnitError> {
-        if self.are_same_dimension(&val.unit, unit).0 {
-            let cf1 = self.get_conversion_factor(&val.unit).unwrap();
-            let cf2 = self.get_conversion_factor(unit).unwrap();
-            Ok(Value::from_value(unit.clone(), val.value * cf1 / cf2))
-        } else {
-            Err(UnitError::BadDimension)
-        }
+        block_on(aio::AsyncUnitConverter::convert(self, val, unit))
+    }
+
+    fn simplify(&self, unit: &Unit) -> Result<Unit, UnitError> {
+        block_on(aio::AsyncUnitConverter::simplify(self, unit))
+    }
+
+    fn simplify_value(&self, value: &Value) -> Result<Value, UnitError> {
+        block_on(aio::AsyncUnitConverter::simplify_value(self, value))
+    }
+
+    fn add(&self, lhs: &Value, rhs: &Value) -> Result<Value, UnitError> {
+        block_on(aio::AsyncUnitConverter::add(self, lhs, rhs))
+    }
+
+    fn sub(&self, lhs: &Value, rhs: &Value) -> Result<Value, UnitError> {
+        block_on(aio::AsyncUnitConverter::sub(self, lhs, rhs))
     }
 
     fn convert_mut(&self, unit1: &mut Value, unit2: &mut Unit) -> Result<Value, UnitError> {
-        todo!()
+        *unit1 = UnitConverter::convert(self, unit1, unit2)?;
+        Ok(Value::from_value(unit1.unit.clone(), unit1.value))
     }
 }
 
-pub async fn construct_all() -> (
-    InlineUnitParser,
-    MainUnitFactory<SqlUnitQuery>,
-    MainConverter<SqlUnitQuery>,
-) {
-    let c = std::rc::Rc::new(SqlUnitQuery::new().await.unwrap());
+/// Builds parser, factory and converter sharing the database found by
+/// [`SqlUnitQuery::locate`](unitquery::SqlUnitQuery::locate), with the user units found by
+/// [`OverlayUnitQuery::locate`] on top.
+pub async fn construct_all(
+    db_path: Option<&Path>,
+    user_db_path: Option<&Path>,
+) -> Result<
+    (
+        InlineUnitParser,
+        MainUnitFactory<OverlayUnitQuery>,
+        MainConverter<OverlayUnitQuery>,
+    ),
+    UnitError,
+> {
+    let c = Arc::new(OverlayUnitQuery::new(db_path, user_db_path).await?);
+    let parser = InlineUnitParser::default();
+    let factory = MainUnitFactory::new(c.clone());
+    let converter = MainConverter::new(c);
+    Ok((parser, factory, converter))
+}
+
+/// Same as [`construct_all`] over the catalogue compiled into the crate, no database file needed.
+#[cfg(feature = "embedded")]
+pub async fn construct_embedded() -> Result<
+    (
+        InlineUnitParser,
+        MainUnitFactory<MemoryUnitQuery>,
+        MainConverter<MemoryUnitQuery>,
+    ),
+    UnitError,
+> {
+    let c = Arc::new(MemoryUnitQuery::embedded().await?);
     let parser = InlineUnitParser::default();
     let factory = MainUnitFactory::new(c.clone());
     let converter = MainConverter::new(c);
-    (parser, factory, converter)
+    Ok((parser, factory, converter))
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "embedded"))]
 mod test {
-    use super::unitquery::SqlUnitQuery;
     use super::*;
+    use crate::unitquery::SqlUnitQuery;
 
     #[tokio::test]
     async fn test_valid_unit() {
-        let c = Rc::new(SqlUnitQuery::new().await.unwrap());
-        let mut converter = MainConverter::new(c);
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let converter = MainConverter::new(c);
+
+        let pu = ElementUnit::new("kg", 1);
+        assert!(converter.is_valid_unit(&pu.into()).unwrap());
 
-        let pu = ElementUnit::new("kg", 1.);
-        assert!(converter.is_valid_unit(&pu.into()));
+        let pu = ElementUnit::new("kg1", 1);
+        assert!(!converter.is_valid_unit(&pu.into()).unwrap());
 
-        let pu = ElementUnit::new("kg1", 1.);
-        assert!(!converter.is_valid_unit(&pu.into()));
+        let pu = ElementUnit::new("mmol", 1);
+        assert!(converter.is_valid_unit(&pu.into()).unwrap());
+
+        let pu = ElementUnit::new("pascal", 1);
+        assert!(converter.is_valid_unit(&pu.into()).unwrap());
     }
 
     #[tokio::test]
     async fn test_same_dimension() {
-        let c = Rc::new(SqlUnitQuery::new().await.unwrap());
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
         let converter = MainConverter::new(c);
-        let pu = ElementUnit::new("kg", 1.);
-        let pu2 = ElementUnit::new("g", 1.);
-
-        assert!(converter.are_same_dimension(&pu.into(), &pu2.into()).0);
-
-        let full_unit =
-            Unit::from_vec(vec![ElementUnit::new("kg", 1.), ElementUnit::new("s", -1.)]);
-        let full_unit2 =
-            Unit::from_vec(vec![ElementUnit::new("g", 1.), ElementUnit::new("h", -1.)]);
-
-        assert!(converter.are_same_dimension(&full_unit, &full_unit2).0);
+        let pu = ElementUnit::new("kg", 1);
+        let pu2 = ElementUnit::new("g", 1);
+
+        assert!(
+            converter
+                .are_same_dimension(&pu.into(), &pu2.into())
+                .unwrap()
+                .0
+        );
+
+        let full_unit = Unit::from_vec(vec![ElementUnit::new("kg", 1), ElementUnit::new("s", -1)]);
+        let full_unit2 = Unit::from_vec(vec![ElementUnit::new("g", 1), ElementUnit::new("h", -1)]);
+
+        assert!(
+            converter
+                .are_same_dimension(&full_unit, &full_unit2)
+                .unwrap()
+                .0
+        );
     }
 
     #[tokio::test]
     async fn test_get_dimension() {
-        let c = Rc::new(SqlUnitQuery::new().await.unwrap());
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
         let converter = MainConverter::new(c);
-        let pu = ElementUnit::new("kg", 1.);
-
-        assert!(converter.get_dimension(&pu.into()) == Dimension([1, 0, 0, 0, 0, 0, 0]));
-
-        let full_unit =
-            Unit::from_vec(vec![ElementUnit::new("kg", 1.), ElementUnit::new("s", -1.)]);
-        assert!(converter.get_dimension(&full_unit.into()) == Dimension([1, -1, 0, 0, 0, 0, 0]));
+        let pu = ElementUnit::new("kg", 1);
+
+        assert!(
+            converter.get_dimension(&pu.into()).unwrap() == Dimension::from([1, 0, 0, 0, 0, 0, 0])
+        );
+
+        let full_unit = Unit::from_vec(vec![ElementUnit::new("kg", 1), ElementUnit::new("s", -1)]);
+        assert!(
+            converter.get_dimension(&full_unit).unwrap() == Dimension::from([1, -1, 0, 0, 0, 0, 0])
+        );
+
+        // Noise spectral density V/sqrt(Hz) keeps its half exponents
+        let full_unit = Unit::from_vec(vec![
+            ElementUnit::new("v", 1),
+            ElementUnit::new("s", Rational::new(1, 2)),
+        ]);
+        let dim = converter.get_dimension(&full_unit).unwrap();
+        assert!(dim.0[1] == Rational::new(-5, 2));
+        assert!(dim.to_string() == "[1, -5/2, 2, 0, -1, 0, 0]");
     }
 
     #[tokio::test]
     async fn test_get_coefficient_factor() {
-        let c = Rc::new(SqlUnitQuery::new().await.unwrap());
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
         let converter = MainConverter::new(c);
-        let pu = ElementUnit::new("g", 1.);
-        let full_unit = Unit::from_vec(vec![ElementUnit::new("g", 1.), ElementUnit::new("h", -1.)]);
+        let pu = ElementUnit::new("g", 1);
+        let full_unit = Unit::from_vec(vec![ElementUnit::new("g", 1), ElementUnit::new("h", -1)]);
 
         assert!(converter.get_conversion_factor(&pu.into()).unwrap() == 1e-3);
         assert!(converter.get_conversion_factor(&full_unit).unwrap() == 1e-3 / 3600.);
@@ -184,11 +287,11 @@ mod test {
 
     #[tokio::test]
     async fn test_convert() {
-        let c = Rc::new(SqlUnitQuery::new().await.unwrap());
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
         let converter = MainConverter::new(c);
-        let pu = ElementUnit::new("g", 1.);
-        let pu2 = ElementUnit::new("kg", 1.);
-        let pu_wrong = ElementUnit::new("m", 1.);
+        let pu = ElementUnit::new("g", 1);
+        let pu2 = ElementUnit::new("kg", 1);
+        let pu_wrong = ElementUnit::new("m", 1);
 
         let value = Value::from_value(pu.into(), 5.0);
 
@@ -196,12 +299,187 @@ mod test {
 
         assert!(converter.convert(&value, &pu_wrong.into()).is_err());
 
-        let full_unit = Unit::from_vec(vec![ElementUnit::new("g", 2.), ElementUnit::new("h", -1.)]);
-        let full_unit2 =
-            Unit::from_vec(vec![ElementUnit::new("kg", 2.), ElementUnit::new("h", -1.)]);
+        let full_unit = Unit::from_vec(vec![ElementUnit::new("g", 2), ElementUnit::new("h", -1)]);
+        let full_unit2 = Unit::from_vec(vec![ElementUnit::new("kg", 2), ElementUnit::new("h", -1)]);
 
         let value = Value::from_value(full_unit, 5.0);
 
         assert!(converter.convert(&value, &full_unit2).unwrap().value == 5. * 1e-6);
+
+        let mut value = Value::from_value(ElementUnit::new("g", 1).into(), 5.0);
+        let mut kg = ElementUnit::new("kg", 1).into();
+        let converted = converter.convert_mut(&mut value, &mut kg).unwrap();
+        assert!(value.value == 5e-3 && converted.value == 5e-3);
+        assert!(value.unit.partials[0].name == "kg");
+    }
+
+    #[tokio::test]
+    async fn test_simplify() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let converter = MainConverter::new(c);
+        let parser = InlineUnitParser::default();
+        let simplify = |text: &str| {
+            parser
+                .parse_unit(text)
+                .unwrap()
+                .simplify()
+                .unwrap()
+                .to_string()
+        };
+
+        assert!(simplify("kg^1*g^1*s^-1*s^1") == "g*kg");
+        assert!(simplify("s^-2*m*kg*m") == "kg*m^2*s^-2");
+        assert!(simplify("m/m").is_empty());
+        assert!(simplify("s^(1/2)*s^(1/2)/Hz") == "s*Hz^-1");
+
+        let named = |text: &str| {
+            let unit = parser.parse_unit(text).unwrap();
+            converter.simplify(&unit).unwrap().to_string()
+        };
+        assert!(named("kg*m^2*s^-2") == "J");
+        assert!(named("m*N") == "J");
+        assert!(named("J/s") == "W");
+        assert!(named("kg/(m*s^2)") == "Pa");
+        // Nothing of that scale, or nothing to gain
+        assert!(named("g*m^2*s^-2") == "g*m^2*s^-2");
+        assert!(named("m^3") == "m^3");
+        assert!(named("J/N/m") == "J*N^-1*m^-1");
+    }
+
+    #[tokio::test]
+    async fn test_value_arithmetic() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let converter = MainConverter::new(c);
+        let parser = InlineUnitParser::default();
+        let value = |value, unit| Value::from_value(parser.parse_unit(unit).unwrap(), value);
+
+        let work = converter
+            .simplify_value(&(value(5., "bar") * value(2., "L")).unwrap())
+            .unwrap();
+        assert!(work.unit == value(1., "J").unit);
+        assert!((work.value - 1000.).abs() < 1e-9);
+        // Nothing named for a speed, units only merged
+        let speed = (value(3., "km") / value(20., "min")).unwrap();
+        let speed = ((speed / value(1., "s")).unwrap() * value(1., "s")).unwrap();
+        let speed = converter.simplify_value(&speed).unwrap();
+        assert!(speed.to_string() == "0.15 km*min^-1");
+
+        let mass = converter.add(&value(1., "kg"), &value(500., "g")).unwrap();
+        assert!(mass.to_string() == "1.5 kg");
+        let mass = converter.sub(&value(1., "kg"), &value(500., "g")).unwrap();
+        assert!(mass.to_string() == "0.5 kg");
+        let warmer = converter.add(&value(20., "degC"), &value(1., "K")).unwrap();
+        assert!(warmer.to_string() == "21 degC");
+        let e = converter
+            .add(&value(1., "m"), &value(1., "s"))
+            .err()
+            .unwrap();
+        let (length, duration) = (
+            Dimension::from([0, 0, 1, 0, 0, 0, 0]),
+            Dimension::from([0, 1, 0, 0, 0, 0, 0]),
+        );
+        assert!(e == UnitError::BadDimension(length, duration));
+        assert!(converter.add(&value(1., "m"), &value(1., "mx")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_convert_temperature() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let converter = MainConverter::new(c);
+        let kelvin: Unit = ElementUnit::new("K", 1).into();
+        let celsius: Unit = ElementUnit::new("degC", 1).into();
+        let fahrenheit: Unit = ElementUnit::new("degF", 1).into();
+
+        let value = Value::from_value(celsius.clone(), 0.);
+        assert!(converter.convert(&value, &kelvin).unwrap().value == 273.15);
+
+        let value = Value::from_value(fahrenheit.clone(), 212.);
+        let res = converter.convert(&value, &celsius).unwrap().value;
+        assert!((res - 100.).abs() < 1e-9);
+
+        let value = Value::from_value(celsius.clone(), -40.);
+        let res = converter.convert(&value, &fahrenheit).unwrap().value;
+        assert!((res + 40.).abs() < 1e-9);
+
+        // Gradients are temperature differences: no offset applied
+        let kelvin_per_m =
+            Unit::from_vec(vec![ElementUnit::new("K", 1), ElementUnit::new("m", -1)]);
+        let celsius_per_m =
+            Unit::from_vec(vec![ElementUnit::new("degC", 1), ElementUnit::new("m", -1)]);
+        let fahrenheit_per_m =
+            Unit::from_vec(vec![ElementUnit::new("degF", 1), ElementUnit::new("m", -1)]);
+        let value = Value::from_value(kelvin_per_m, 5.);
+        assert!(converter.convert(&value, &celsius_per_m).unwrap().value == 5.);
+        let res = converter.convert(&value, &fahrenheit_per_m).unwrap().value;
+        assert!((res - 9.).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_convert_defined() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let converter = MainConverter::new(c);
+        let kwh: Unit = ElementUnit::new("kWh", 1).into();
+        let mut kw_h = Unit::from_vec(vec![ElementUnit::new("kW", 1), ElementUnit::new("h", 1)]);
+
+        let value = Value::from_value(ElementUnit::new("MJ", 1).into(), 3.6);
+        let res = converter.convert(&value, &kwh).unwrap().value;
+        assert!((res - 1.).abs() < 1e-9);
+        assert!(converter.are_same_dimension(&kwh, &kw_h).unwrap().0);
+        converter.get_dimension_mut(&mut kw_h).unwrap();
+        assert!(kw_h.partials[0].dim.is_none());
+        assert!(kw_h.partials[1].dim == Some("duration".to_owned()));
+
+        let force = Unit::from_vec(vec![ElementUnit::new("N", 1)]);
+        assert!(
+            converter.get_dimension(&force).unwrap() == Dimension::from([1, -2, 1, 0, 0, 0, 0])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_unit() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let converter = MainConverter::new(c);
+        let kg: Unit = ElementUnit::new("kg", 1).into();
+
+        // Unknown units are reported instead of panicking
+        let mut unknown: Unit = ElementUnit::new("FALSEUNIT", 1).into();
+        assert!(converter.get_dimension(&unknown).is_err());
+        assert!(converter.get_dimension_mut(&mut unknown).is_err());
+        assert!(converter.are_same_dimension(&kg, &unknown).is_err());
+        let product = Unit::from_vec(vec![
+            ElementUnit::new("FALSEUNIT", 1),
+            ElementUnit::new("kg", 1),
+        ]);
+        assert!(converter.simplify(&product).is_err());
+        let value = Value::from_value(kg.clone(), 1.);
+        assert!(converter.convert(&value, &unknown).is_err());
+        let value = Value::from_value(unknown, 1.);
+        assert!(converter.convert(&value, &kg).is_err());
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<MainConverter<SqlUnitQuery>>();
+        assert_send_sync::<MainUnitFactory<SqlUnitQuery>>();
+        assert_send_sync::<MainConverter<MemoryUnitQuery>>();
+    }
+
+    #[tokio::test]
+    async fn test_parallel_convert() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let converter = MainConverter::new(c);
+        let km: Unit = ElementUnit::new("km", 1).into();
+
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let (converter, km) = (&converter, &km);
+                s.spawn(move || {
+                    assert!(converter.is_valid_unit(km).unwrap());
+                    let value = Value::from_value(ElementUnit::new("m", 1).into(), i as f64 * 1e3);
+                    assert!(converter.convert(&value, km).unwrap().value == i as f64);
+                });
+            }
+        });
     }
 }
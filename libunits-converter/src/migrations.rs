@@ -0,0 +1,438 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Versioned schema migrations.
+//!
+//! A migration is a `NNN_description.sql` file, `NNN` being its version. Applied versions are
+//! recorded in the `schema_version` table so that an existing database is only brought up to
+//! date with the migrations it is missing, each one in a transaction of its own.
+
+use std::path::Path;
+
+use crate::UnitError;
+
+/// Version of the last migration known to this crate, the schema its queries are written for.
+pub const LATEST: u32 = 10;
+
+/// Content of the migration files compiled into the crate, in application order.
+#[cfg(feature = "embedded")]
+pub const EMBEDDED: [(&str, &str); 9] = [
+    (
+        "002_dimensions.sql",
+        include_str!("../../migrations/002_dimensions.sql"),
+    ),
+    (
+        "003_conversion.sql",
+        include_str!("../../migrations/003_conversion.sql"),
+    ),
+    (
+        "004_temperature_offset.sql",
+        include_str!("../../migrations/004_temperature_offset.sql"),
+    ),
+    (
+        "005_prefixes.sql",
+        include_str!("../../migrations/005_prefixes.sql"),
+    ),
+    (
+        "006_imperial_units.sql",
+        include_str!("../../migrations/006_imperial_units.sql"),
+    ),
+    (
+        "007_definitions.sql",
+        include_str!("../../migrations/007_definitions.sql"),
+    ),
+    (
+        "008_aliases.sql",
+        include_str!("../../migrations/008_aliases.sql"),
+    ),
+    (
+        "009_angstrom.sql",
+        include_str!("../../migrations/009_angstrom.sql"),
+    ),
+    (
+        "010_quantities.sql",
+        include_str!("../../migrations/010_quantities.sql"),
+    ),
+];
+
+const SCHEMA_VERSION: &str = "CREATE TABLE IF NOT EXISTS schema_version (
+  version integer PRIMARY KEY
+, name varchar(255) NOT NULL
+, applied_at text DEFAULT CURRENT_TIMESTAMP
+)";
+
+/// Probes identifying how far a catalogue built before `schema_version` existed went, most
+/// recent first: the version and a query failing or returning nothing below it.
+const LEGACY_PROBES: [(u32, &str); 6] = [
+    (7, "SELECT definition FROM conversiontable LIMIT 1"),
+    (6, "SELECT id FROM conversiontable WHERE unit_name = 'ft'"),
+    (5, "SELECT si_prefix FROM conversiontable LIMIT 1"),
+    (4, "SELECT conversionoffset FROM conversiontable LIMIT 1"),
+    (3, "SELECT id FROM conversiontable LIMIT 1"),
+    (2, "SELECT iddimension FROM dimension LIMIT 1"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Migration {
+    pub version: u32,
+    /// File name, `007_definitions.sql`.
+    pub name: String,
+    pub sql: String,
+}
+
+impl Migration {
+    /// Migration read from a file named `NNN_description.sql`, `None` for any other name.
+    pub fn new(name: &str, sql: impl Into<String>) -> Option<Self> {
+        let (version, rest) = name.split_once('_')?;
+        if version.len() != 3 || !version.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        if rest.len() <= ".sql".len() || !rest.ends_with(".sql") {
+            return None;
+        }
+        Some(Self {
+            version: version.parse().ok()?,
+            name: name.to_owned(),
+            sql: sql.into(),
+        })
+    }
+}
+
+fn migration_error(name: &str, e: impl std::fmt::Display) -> UnitError {
+    UnitError::Query(format!("Migration {}: {}", name, e))
+}
+
+/// Sorts `migrations` by version, two files sharing a version are an error.
+fn ordered(mut migrations: Vec<Migration>) -> Result<Vec<Migration>, UnitError> {
+    migrations.sort_by_key(|m| m.version);
+    if let Some(pair) = migrations.windows(2).find(|w| w[0].version == w[1].version) {
+        return Err(migration_error(
+            &pair[1].name,
+            format!(
+                "version {} already used by {}",
+                pair[1].version, pair[0].name
+            ),
+        ));
+    }
+    Ok(migrations)
+}
+
+/// Migration files of `dir` ordered by version, other files are ignored.
+pub fn discover(dir: &Path) -> Result<Vec<Migration>, UnitError> {
+    let dir_error = |e: std::io::Error| migration_error(&dir.display().to_string(), e);
+    let mut migrations = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(dir_error)? {
+        let path = entry.map_err(dir_error)?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if Migration::new(name, "").is_none() {
+            continue;
+        }
+        let sql = std::fs::read_to_string(&path).map_err(|e| migration_error(name, e))?;
+        migrations.extend(Migration::new(name, sql));
+    }
+    ordered(migrations)
+}
+
+/// [`EMBEDDED`] migrations ordered by version.
+#[cfg(feature = "embedded")]
+pub fn embedded() -> Vec<Migration> {
+    ordered(
+        EMBEDDED
+            .iter()
+            .filter_map(|(name, sql)| Migration::new(name, *sql))
+            .collect(),
+    )
+    .expect("embedded migrations have distinct versions")
+}
+
+/// Statements of a migration file. Semicolons inside quotes or comments do not end a statement,
+/// transaction control is dropped since [`apply`] runs each migration in its own transaction.
+pub fn statements(sql: &str) -> impl Iterator<Item = &str> {
+    let mut statements = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+    let mut start = 0;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                // A doubled quote is an escaped one and simply reopens the literal
+                for (_, q) in chars.by_ref() {
+                    if q == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.next_if(|&(_, c)| c == '-').is_some() => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.next_if(|&(_, c)| c == '*').is_some() => {
+                while let Some((_, c)) = chars.next() {
+                    if c == '*' && chars.next_if(|&(_, c)| c == '/').is_some() {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                statements.push(&sql[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push(&sql[start..]);
+    statements
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty() && !is_transaction_control(s))
+}
+
+fn is_transaction_control(statement: &str) -> bool {
+    let words: Vec<String> = statement
+        .split_whitespace()
+        .map(str::to_ascii_uppercase)
+        .collect();
+    matches!(
+        words.iter().map(String::as_str).collect::<Vec<_>>()[..],
+        ["BEGIN"] | ["BEGIN", "TRANSACTION"] | ["COMMIT"] | ["END"] | ["END", "TRANSACTION"]
+    )
+}
+
+async fn has_table(conn: &turso::Connection, table: &str) -> Result<bool, UnitError> {
+    let mut rows = conn
+        .query(
+            "SELECT name FROM sqlite_schema WHERE type = 'table' AND name = ?1",
+            [table],
+        )
+        .await
+        .map_err(|e| migration_error("schema_version", e))?;
+    Ok(rows
+        .next()
+        .await
+        .map_err(|e| migration_error("schema_version", e))?
+        .is_some())
+}
+
+/// Highest version applied to the database behind `conn`, 0 when none is recorded.
+pub async fn current_version(conn: &turso::Connection) -> Result<u32, UnitError> {
+    if !has_table(conn, "schema_version").await? {
+        return Ok(0);
+    }
+    let mut rows = conn
+        .query("SELECT MAX(version) FROM schema_version", ())
+        .await
+        .map_err(|e| migration_error("schema_version", e))?;
+    let row = rows
+        .next()
+        .await
+        .map_err(|e| migration_error("schema_version", e))?;
+    match row.map(|row| row.get_value(0)) {
+        Some(Ok(turso::Value::Integer(version))) => Ok(version as u32),
+        _ => Ok(0),
+    }
+}
+
+/// Version reached by a catalogue populated before versions were recorded, `None` when the
+/// database has a `schema_version` table or no catalogue at all.
+pub async fn legacy_version(conn: &turso::Connection) -> Result<Option<u32>, UnitError> {
+    if has_table(conn, "schema_version").await? {
+        return Ok(None);
+    }
+    for (version, probe) in LEGACY_PROBES {
+        // A missing table or column fails the query
+        let Ok(mut rows) = conn.query(probe, ()).await else {
+            continue;
+        };
+        if let Ok(Some(_)) = rows.next().await {
+            return Ok(Some(version));
+        }
+    }
+    Ok(None)
+}
+
+/// Migrations of `migrations` above `version`.
+pub fn pending(migrations: &[Migration], version: u32) -> Vec<&Migration> {
+    migrations.iter().filter(|m| m.version > version).collect()
+}
+
+/// Records every migration up to `version` as applied without running it, for a catalogue
+/// found at that version by [`legacy_version`].
+pub async fn baseline(
+    conn: &turso::Connection,
+    migrations: &[Migration],
+    version: u32,
+) -> Result<(), UnitError> {
+    for migration in migrations.iter().filter(|m| m.version <= version) {
+        record(conn, migration).await?;
+    }
+    Ok(())
+}
+
+async fn record(conn: &turso::Connection, migration: &Migration) -> Result<(), UnitError> {
+    let error = |e| migration_error(&migration.name, e);
+    conn.execute(SCHEMA_VERSION, ()).await.map_err(error)?;
+    conn.execute(
+        "INSERT INTO schema_version (version, name) VALUES (?1, ?2)",
+        vec![
+            turso::Value::Integer(migration.version.into()),
+            turso::Value::Text(migration.name.clone()),
+        ],
+    )
+    .await
+    .map_err(error)?;
+    Ok(())
+}
+
+/// Runs `migration` and records it in a single transaction, nothing is left behind on error.
+pub async fn apply(conn: &turso::Connection, migration: &Migration) -> Result<(), UnitError> {
+    let error = |e| migration_error(&migration.name, e);
+    conn.execute("BEGIN", ()).await.map_err(error)?;
+    let mut result = Ok(());
+    for statement in statements(&migration.sql) {
+        result = conn.execute(statement, ()).await.map(|_| ()).map_err(error);
+        if result.is_err() {
+            break;
+        }
+    }
+    if result.is_ok() {
+        result = record(conn, migration).await;
+    }
+    match result {
+        Ok(()) => conn.execute("COMMIT", ()).await.map(|_| ()).map_err(error),
+        Err(e) => {
+            conn.execute("ROLLBACK", ()).await.map_err(error)?;
+            Err(e)
+        }
+    }
+}
+
+/// Brings the database behind `conn` up to the last of `migrations`, returns those applied.
+pub async fn migrate<'a>(
+    conn: &turso::Connection,
+    migrations: &'a [Migration],
+) -> Result<Vec<&'a Migration>, UnitError> {
+    let pending = pending(migrations, current_version(conn).await?);
+    for migration in &pending {
+        apply(conn, migration).await?;
+    }
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn memory() -> (turso::Database, turso::Connection) {
+        let db = turso::Builder::new_local(":memory:").build().await.unwrap();
+        let conn = db.connect().unwrap();
+        (db, conn)
+    }
+
+    fn migration(name: &str, sql: &str) -> Migration {
+        Migration::new(name, sql).unwrap()
+    }
+
+    #[test]
+    fn test_migration_name() {
+        assert!(migration("007_definitions.sql", "").version == 7);
+        assert!(Migration::new("7_definitions.sql", "").is_none());
+        assert!(Migration::new("007_definitions.txt", "").is_none());
+        assert!(Migration::new("007_.sql", "").is_none());
+        assert!(Migration::new("README.md", "").is_none());
+
+        let res = ordered(vec![migration("002_a.sql", ""), migration("002_b.sql", "")]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_statements() {
+        let sql = "BEGIN TRANSACTION;
+INSERT INTO t VALUES('a;b'); -- trailing; comment
+INSERT INTO t VALUES('ft''');
+/* block; comment */ INSERT INTO t VALUES(\"in;\");
+COMMIT;";
+        let statements: Vec<_> = statements(sql).collect();
+        assert!(statements.len() == 3, "{:?}", statements);
+        assert!(statements[0] == "INSERT INTO t VALUES('a;b')");
+        assert!(statements[1].ends_with("VALUES('ft''')"));
+        assert!(statements[2].ends_with("VALUES(\"in;\")"));
+    }
+
+    #[test]
+    fn test_discover() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../migrations");
+        let migrations = discover(&dir).unwrap();
+        assert!(migrations.windows(2).all(|w| w[0].version < w[1].version));
+        assert!(migrations.last().unwrap().version == LATEST);
+        #[cfg(feature = "embedded")]
+        assert!(migrations == embedded());
+    }
+
+    #[tokio::test]
+    async fn test_incremental() {
+        let (_db, conn) = memory().await;
+        let mut migrations = vec![
+            migration("001_create.sql", "CREATE TABLE t (a integer);"),
+            migration("002_insert.sql", "INSERT INTO t VALUES (1);"),
+        ];
+        assert!(current_version(&conn).await.unwrap() == 0);
+        assert!(migrate(&conn, &migrations).await.unwrap().len() == 2);
+        assert!(current_version(&conn).await.unwrap() == 2);
+        assert!(migrate(&conn, &migrations).await.unwrap().is_empty());
+
+        migrations.push(migration(
+            "003_column.sql",
+            "ALTER TABLE t ADD COLUMN b text;",
+        ));
+        let applied = migrate(&conn, &migrations).await.unwrap();
+        assert!(applied.len() == 1 && applied[0].version == 3);
+        assert!(conn.query("SELECT b FROM t", ()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rollback() {
+        let (_db, conn) = memory().await;
+        let migrations = vec![
+            migration("001_create.sql", "CREATE TABLE t (a integer);"),
+            migration(
+                "002_broken.sql",
+                "INSERT INTO t VALUES (1); INSERT INTO missing VALUES (1);",
+            ),
+        ];
+        assert!(migrate(&conn, &migrations).await.is_err());
+        assert!(current_version(&conn).await.unwrap() == 1);
+        let mut rows = conn.query("SELECT a FROM t", ()).await.unwrap();
+        assert!(rows.next().await.unwrap().is_none());
+    }
+
+    #[cfg(feature = "embedded")]
+    #[tokio::test]
+    async fn test_legacy_catalogue() {
+        let (_db, conn) = memory().await;
+        let migrations = embedded();
+        assert!(legacy_version(&conn).await.unwrap().is_none());
+        // Catalogue built by the former init, up to the imperial units
+        for migration in &migrations[..5] {
+            for statement in statements(&migration.sql) {
+                conn.execute(statement, ()).await.unwrap();
+            }
+        }
+        assert!(legacy_version(&conn).await.unwrap() == Some(6));
+        baseline(&conn, &migrations, 6).await.unwrap();
+        assert!(legacy_version(&conn).await.unwrap().is_none());
+        let applied = migrate(&conn, &migrations).await.unwrap();
+        let names: Vec<&str> = applied.iter().map(|m| m.name.as_str()).collect();
+        assert!(
+            names
+                == [
+                    "007_definitions.sql",
+                    "008_aliases.sql",
+                    "009_angstrom.sql",
+                    "010_quantities.sql"
+                ]
+        );
+    }
+}
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::{ElementUnit, Unit, UnitError};
+use crate::{ElementUnit, Rational, Unit, UnitError};
 const UNSAFE_CHAR: [char; 8] = [';', '&', '|', '>', '<', '$', '!', '`'];
 fn is_safe(input: &str) -> bool {
     input.chars().all(|c| !UNSAFE_CHAR.contains(&c))
@@ -27,7 +27,298 @@ impl Default for InlineUnitParser {
         }
     }
 }
-const FULL_PARSE_N_SPLIT: usize = 2;
+/// Multiplication signs accepted besides the delimiter, as found in papers and datasheets.
+const PRODUCT_SIGNS: [char; 3] = ['·', '⋅', '×'];
+
+/// Superscript digits, in order from 0 to 9.
+const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+/// Spellings rewritten to their catalogue form inside unit names, prefixes kept (`kΩ` is `kohm`).
+/// Look-alike code points (ohm and angstrom signs, greek mu, one-character degrees) come first
+/// so that they share the rewriting of the usual one.
+const SYMBOLS: [(&str, &str); 10] = [
+    ("\u{2126}", "Ω"),
+    ("\u{212B}", "Å"),
+    ("μ", "µ"),
+    ("℃", "°C"),
+    ("℉", "°F"),
+    ("Ω", "ohm"),
+    ("°C", "degC"),
+    ("°F", "degF"),
+    ("°R", "degR"),
+    ("Å", "angstrom"),
+];
+
+/// LaTeX wrapping a whole unit, `\mathrm{kg\,m^{2}}`, and the thin space multiplying in it.
+const LATEX_UPRIGHT: &str = "\\mathrm{";
+const LATEX_SPACE: &str = "\\,";
+
+fn is_superscript(c: char) -> bool {
+    SUPERSCRIPT_DIGITS.contains(&c) || c == '⁻' || c == '⁺'
+}
+
+/// Catalogue form of a unit name written with symbols (`µΩ`, `°C`, `Å`).
+fn normalise_name(name: &str) -> String {
+    SYMBOLS
+        .iter()
+        .fold(name.to_owned(), |name, (symbol, canonical)| {
+            name.replace(symbol, canonical)
+        })
+}
+
+/// Recursive-descent parser over the unit grammar:
+///
+/// ```text
+/// input    := unit | '\mathrm{' unit '}'
+/// unit     := term ((delimiter | '·' | '×' | '\,' | '/') term)*
+/// term     := atom (exp_symbol exponent | superscript)?
+/// atom     := name | '(' unit ')'
+/// exponent := number | ('(' | '{') number ('/' number)? (')' | '}')
+/// ```
+///
+/// A factor without exponent is raised to 1, a term after `/` has its exponents negated and an
+/// exponent applied to a parenthesised group is distributed over every unit of the group.
+/// Superscript exponents are integers (`m²`, `s⁻¹`), names are [normalised](normalise_name).
+struct ExprParser<'a> {
+    text: &'a str,
+    pos: usize,
+    delimiter: &'a str,
+    exp_symbol: &'a str,
+}
+
+impl<'a> ExprParser<'a> {
+    fn new(text: &'a str, delimiter: &'a str, exp_symbol: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            delimiter,
+            exp_symbol,
+        }
+    }
+
+    fn error(&self, expected: &str) -> UnitError {
+        match self.rest().chars().next() {
+            Some(c) => UnitError::parse(
+                self.text,
+                format!("expected {} but found '{}'", expected, c),
+                Some(self.pos..self.pos + c.len_utf8()),
+            ),
+            None => UnitError::parse(
+                self.text,
+                format!("expected {} but reached end of input", expected),
+                Some(self.pos..self.pos),
+            ),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.text.len() - trimmed.len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if !token.is_empty() && self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_operator(&self, rest: &str) -> bool {
+        rest.starts_with(['(', ')', '/', '{', '}', '\\'])
+            || rest.starts_with(PRODUCT_SIGNS)
+            || rest.starts_with(is_superscript)
+            || (!self.delimiter.is_empty() && rest.starts_with(self.delimiter))
+            || (!self.exp_symbol.is_empty() && rest.starts_with(self.exp_symbol))
+    }
+
+    // Delimiter and exponent symbol may share a prefix (e.g. `*` and `**`), the longest wins.
+    fn eat_exp_symbol(&mut self) -> bool {
+        self.skip_whitespace();
+        if self.exp_symbol.len() < self.delimiter.len() && self.rest().starts_with(self.delimiter) {
+            return false;
+        }
+        self.eat(self.exp_symbol)
+    }
+
+    fn eat_delimiter(&mut self) -> bool {
+        self.skip_whitespace();
+        if let Some(sign) = self
+            .rest()
+            .chars()
+            .next()
+            .filter(|c| PRODUCT_SIGNS.contains(c))
+        {
+            self.pos += sign.len_utf8();
+            return true;
+        }
+        if self.eat(LATEX_SPACE) {
+            return true;
+        }
+        if self.delimiter.len() < self.exp_symbol.len() && self.rest().starts_with(self.exp_symbol)
+        {
+            return false;
+        }
+        self.eat(self.delimiter)
+    }
+
+    fn parse(mut self) -> Result<Vec<ElementUnit>, UnitError> {
+        let latex = self.eat(LATEX_UPRIGHT);
+        let partials = self.parse_unit()?;
+        if latex && !self.eat("}") {
+            return Err(self.error("'}'"));
+        }
+        self.skip_whitespace();
+        if !self.rest().is_empty() {
+            return Err(self.error("end of input"));
+        }
+        Ok(partials)
+    }
+
+    fn parse_unit(&mut self) -> Result<Vec<ElementUnit>, UnitError> {
+        let mut partials = self.parse_term()?;
+        loop {
+            if self.eat_delimiter() {
+                partials.extend(self.parse_term()?);
+            } else if self.eat("/") {
+                partials.extend(self.parse_term()?.into_iter().map(|mut p| {
+                    p.set_exp(-p.exp());
+                    p
+                }));
+            } else {
+                return Ok(partials);
+            }
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Vec<ElementUnit>, UnitError> {
+        let mut partials = self.parse_atom()?;
+        let exp = if self.rest().starts_with(is_superscript) {
+            Some(self.parse_superscript()?)
+        } else if self.eat_exp_symbol() {
+            Some(self.parse_exponent()?)
+        } else {
+            None
+        };
+        if let Some(exp) = exp {
+            for p in partials.iter_mut() {
+                p.set_exp(p.exp().checked_mul(exp)?);
+            }
+        }
+        Ok(partials)
+    }
+
+    fn parse_atom(&mut self) -> Result<Vec<ElementUnit>, UnitError> {
+        if self.eat("(") {
+            let partials = self.parse_unit()?;
+            if !self.eat(")") {
+                return Err(self.error("')'"));
+            }
+            return Ok(partials);
+        }
+
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|(i, c)| c.is_whitespace() || self.is_operator(&rest[*i..]))
+            .map_or(rest.len(), |(i, _)| i);
+        if len == 0 {
+            return Err(self.error("unit name"));
+        }
+        self.pos += len;
+        let mut unit = ElementUnit::new(&normalise_name(&rest[..len]), 1);
+        unit.set_span(Some(self.pos - len..self.pos));
+        Ok(vec![unit])
+    }
+
+    fn parse_exponent(&mut self) -> Result<Rational, UnitError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let close = if self.eat("(") {
+            Some(")")
+        } else if self.eat("{") {
+            Some("}")
+        } else {
+            None
+        };
+        if let Some(close) = close {
+            let mut exp = self.parse_number()?;
+            if self.eat("/") {
+                let denominator = self.parse_number()?;
+                if denominator.is_zero() {
+                    return Err(UnitError::parse(
+                        self.text,
+                        "null denominator in exponent",
+                        Some(start..self.pos),
+                    ));
+                }
+                exp = exp.checked_div(denominator).map_err(|e| {
+                    UnitError::parse(self.text, e.to_string(), Some(start..self.pos))
+                })?;
+            }
+            if !self.eat(close) {
+                return Err(self.error(&format!("'{}'", close)));
+            }
+            return Ok(exp);
+        }
+        self.parse_number()
+    }
+
+    /// Integer written in superscript right after its unit, `⁻¹`.
+    fn parse_superscript(&mut self) -> Result<Rational, UnitError> {
+        let rest = self.rest();
+        let (negative, sign_len) = match rest.chars().next() {
+            Some(sign @ ('⁻' | '⁺')) => (sign == '⁻', sign.len_utf8()),
+            _ => (false, 0),
+        };
+        let mut value: i32 = 0;
+        let mut len = sign_len;
+        for c in rest[sign_len..].chars() {
+            let Some(digit) = SUPERSCRIPT_DIGITS.iter().position(|d| *d == c) else {
+                break;
+            };
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add(digit as i32))
+                .ok_or_else(|| self.error("smaller exponent"))?;
+            len += c.len_utf8();
+        }
+        if len == sign_len {
+            self.pos += sign_len;
+            return Err(self.error("superscript digit"));
+        }
+        self.pos += len;
+        Ok(Rational::from(if negative { -value } else { value }))
+    }
+
+    fn parse_number(&mut self) -> Result<Rational, UnitError> {
+        self.skip_whitespace();
+        // Typeset minus sign, `m^−1`
+        if self.rest().starts_with('−') {
+            self.pos += '−'.len_utf8();
+            return Ok(-self.parse_number()?);
+        }
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|(i, c)| {
+                !(c.is_ascii_digit() || *c == '.' || (*i == 0 && (*c == '-' || *c == '+')))
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        let number = rest[..len]
+            .parse::<Rational>()
+            .map_err(|_| self.error("exponent"))?;
+        self.pos += len;
+        Ok(number)
+    }
+}
 
 impl UnitParser for InlineUnitParser {
     fn set_delimiter(&mut self, delimiter: &str) -> bool {
@@ -55,35 +346,32 @@ impl UnitParser for InlineUnitParser {
     }
 
     fn parse_element_unit(&self, text: &str) -> Result<ElementUnit, UnitError> {
-        if !is_safe(text) {
-            return Err(UnitError::BadUnit("Impossible to parse".to_owned()));
-        }
-        let splited: Vec<&str> = text.split(&self.exp_symbol).collect();
-        if splited.len() == FULL_PARSE_N_SPLIT {
-            Ok(ElementUnit::new(
-                splited[0],
-                splited[1]
-                    .parse::<f64>()
-                    .map_err(|_| UnitError::ParseError("Bad exponential".to_owned()))?,
-            ))
+        let mut partials = self.parse_unit(text)?.partials;
+        if partials.len() == 1 {
+            Ok(partials.remove(0))
         } else {
-            Err(UnitError::ParseError(text.to_owned()))
+            Err(UnitError::parse(text, "expected a single unit", None))
         }
     }
 
     fn parse_unit(&self, text: &str) -> Result<Unit, UnitError> {
-        let vec_u: Result<Vec<ElementUnit>, UnitError> = text
-            .split(&self.delimiter)
-            .map(|pu_str| self.parse_element_unit(pu_str))
-            .collect();
-
-        Ok(Unit::from_vec(vec_u?))
+        if let Some(i) = text.find(UNSAFE_CHAR) {
+            let c = text[i..].chars().next().unwrap();
+            return Err(UnitError::parse(
+                text,
+                format!("forbidden character '{}'", c),
+                Some(i..i + c.len_utf8()),
+            ));
+        }
+        let partials = ExprParser::new(text, &self.delimiter, &self.exp_symbol).parse()?;
+        Ok(Unit::from_vec(partials))
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::{InlineUnitParser, UnitParser};
+    use crate::Rational;
     use proptest::prelude::*;
     #[test]
     fn t_simple_parse_element_unit() {
@@ -91,7 +379,7 @@ mod test {
         let parser = InlineUnitParser::default();
         let unit = parser.parse_element_unit(input).unwrap();
         assert!(unit.name == *"kg");
-        assert!(unit.exp() == 1.);
+        assert!(unit.exp() == Rational::from(1));
     }
 
     #[test]
@@ -100,7 +388,7 @@ mod test {
         let parser = InlineUnitParser::default();
         let unit = parser.parse_element_unit(input).unwrap();
         assert!(unit.name == *"Pa");
-        assert!(unit.exp() == -5.);
+        assert!(unit.exp() == Rational::from(-5));
     }
 
     #[test]
@@ -111,7 +399,7 @@ mod test {
         assert!(unit.partials.len() == 1);
 
         assert!(unit.partials[0].name == *"kg");
-        assert!(unit.partials[0].exp() == 1.);
+        assert!(unit.partials[0].exp() == Rational::from(1));
     }
     #[test]
     fn t_parse_unit() {
@@ -121,10 +409,158 @@ mod test {
         assert!(unit.partials.len() == 2);
 
         assert!(unit.partials[0].name == *"kg");
-        assert!(unit.partials[0].exp() == 1.);
+        assert!(unit.partials[0].exp() == Rational::from(1));
 
         assert!(unit.partials[1].name == *"Pa");
-        assert!(unit.partials[1].exp() == -6.);
+        assert!(unit.partials[1].exp() == Rational::from(-6));
+    }
+
+    #[test]
+    fn t_parse_implicit_exponent() {
+        let parser = InlineUnitParser::default();
+        let unit = parser.parse_unit("kg").unwrap();
+        assert!(unit.partials.len() == 1);
+        assert!(unit.partials[0].name == *"kg");
+        assert!(unit.partials[0].exp() == Rational::from(1));
+
+        let unit = parser.parse_element_unit("m3").unwrap();
+        assert!(unit.name == *"m3");
+        assert!(unit.exp() == Rational::from(1));
+    }
+
+    #[test]
+    fn t_parse_division() {
+        let parser = InlineUnitParser::default();
+        let unit = parser.parse_unit("kg*m/s^2").unwrap();
+        let parts: Vec<(&str, f64)> = unit
+            .partials
+            .iter()
+            .map(|p| (p.name.as_str(), p.exp().to_f64()))
+            .collect();
+        assert!(parts == vec![("kg", 1.), ("m", 1.), ("s", -2.)]);
+
+        let unit = parser.parse_unit("m/s/s").unwrap();
+        assert!(unit.partials[1].exp() == Rational::from(-1));
+        assert!(unit.partials[2].exp() == Rational::from(-1));
+    }
+
+    #[test]
+    fn t_parse_parentheses() {
+        let parser = InlineUnitParser::default();
+        let unit = parser.parse_unit("(kg*m)/(s^2*A)").unwrap();
+        let parts: Vec<(&str, f64)> = unit
+            .partials
+            .iter()
+            .map(|p| (p.name.as_str(), p.exp().to_f64()))
+            .collect();
+        assert!(parts == vec![("kg", 1.), ("m", 1.), ("s", -2.), ("A", -1.)]);
+
+        let unit = parser.parse_unit("(m/s)^2").unwrap();
+        assert!(unit.partials[0].exp() == Rational::from(2));
+        assert!(unit.partials[1].exp() == Rational::from(-2));
+    }
+
+    #[test]
+    fn t_parse_fractional_exponent() {
+        let parser = InlineUnitParser::default();
+        let unit = parser.parse_unit("m^(1/2)").unwrap();
+        assert!(unit.partials[0].exp() == Rational::new(1, 2));
+
+        let unit = parser.parse_unit("V/Hz^(-1/2)").unwrap();
+        assert!(unit.partials[1].exp() == Rational::new(1, 2));
+
+        let unit = parser.parse_unit("m^0.5").unwrap();
+        assert!(unit.partials[0].exp() == Rational::new(1, 2));
+    }
+
+    #[test]
+    fn t_parse_custom_symbols() {
+        let mut parser = InlineUnitParser::default();
+        parser.set_delimiter(".");
+        parser.set_exp_symbol("**");
+        let unit = parser.parse_unit("kg.m**2/s**2").unwrap();
+        let parts: Vec<(&str, f64)> = unit
+            .partials
+            .iter()
+            .map(|p| (p.name.as_str(), p.exp().to_f64()))
+            .collect();
+        assert!(parts == vec![("kg", 1.), ("m", 2.), ("s", -2.)]);
+
+        parser.set_delimiter("*");
+        let unit = parser.parse_unit("kg*m**2").unwrap();
+        assert!(unit.partials.len() == 2);
+        assert!(unit.partials[1].exp() == Rational::from(2));
+    }
+
+    #[test]
+    fn t_parse_errors() {
+        let parser = InlineUnitParser::default();
+        assert!(parser.parse_unit("").is_err());
+        assert!(parser.parse_unit("kg*").is_err());
+        assert!(parser.parse_unit("(kg*m").is_err());
+        assert!(parser.parse_unit("kg)").is_err());
+        assert!(parser.parse_unit("m^").is_err());
+        assert!(parser.parse_unit("m^(1/0)").is_err());
+        assert!(parser.parse_element_unit("kg*m").is_err());
+    }
+
+    #[test]
+    fn t_parse_unicode() {
+        let parser = InlineUnitParser::default();
+        let parts = |text: &str| -> Vec<(String, f64)> {
+            let unit = parser.parse_unit(text).unwrap();
+            unit.partials
+                .iter()
+                .map(|p| (p.name.clone(), p.exp().to_f64()))
+                .collect()
+        };
+        let expected = |parts: &[(&str, f64)]| -> Vec<(String, f64)> {
+            parts.iter().map(|(n, e)| (n.to_string(), *e)).collect()
+        };
+        assert!(parts("kg·m²·s⁻²") == expected(&[("kg", 1.), ("m", 2.), ("s", -2.)]));
+        assert!(parts("N × m") == expected(&[("N", 1.), ("m", 1.)]));
+        assert!(parts("m⋅s⁻¹") == expected(&[("m", 1.), ("s", -1.)]));
+        assert!(parts("(m/s)²") == expected(&[("m", 2.), ("s", -2.)]));
+        assert!(parts("m³/kg") == expected(&[("m", 3.), ("kg", -1.)]));
+        assert!(parts("m^−1") == expected(&[("m", -1.)]));
+        assert!(parts("s¹⁰") == expected(&[("s", 10.)]));
+
+        // Symbols take their catalogue spelling, prefixes kept
+        assert!(parts("kΩ") == expected(&[("kohm", 1.)]));
+        assert!(parts("Ω·m") == expected(&[("ohm", 1.), ("m", 1.)]));
+        assert!(parts("μm") == expected(&[("µm", 1.)]));
+        assert!(parts("°C") == expected(&[("degC", 1.)]));
+        assert!(parts("℉/s") == expected(&[("degF", 1.), ("s", -1.)]));
+        assert!(parts("Å") == expected(&[("angstrom", 1.)]));
+        assert!(parts("\u{212B}") == expected(&[("angstrom", 1.)]));
+        assert!(parts("M\u{2126}") == expected(&[("Mohm", 1.)]));
+
+        // Spans point into the text as typed
+        let unit = parser.parse_unit("µΩ·m²").unwrap();
+        assert!(unit.partials[0].span() == Some(0..4));
+        assert!(unit.partials[1].span() == Some(6..7));
+        assert!(parser.parse_unit("m⁻").is_err());
+        assert!(parser.parse_unit("m²³⁴⁵⁶⁷⁸⁹⁰¹²").is_err());
+        assert!(parser.parse_unit("·m").is_err());
+    }
+
+    #[test]
+    fn t_parse_spans() {
+        let parser = InlineUnitParser::default();
+        let unit = parser.parse_unit("kg * m^2/s").unwrap();
+        let spans: Vec<_> = unit.partials.iter().map(|p| p.span()).collect();
+        assert!(spans == vec![Some(0..2), Some(5..6), Some(9..10)]);
+
+        let e = parser.parse_unit("kg)").err().unwrap();
+        assert!(e.span() == Some(2..3));
+        let e = parser.parse_unit("m^").err().unwrap();
+        assert!(e.span() == Some(2..2));
+        let e = parser.parse_unit("m^(1/0)").err().unwrap();
+        assert!(e.highlight("m^(1/0)") == Some(("m^", "(1/0", ")")));
+        let e = parser.parse_unit("m^(2147483647/0.5)").err().unwrap();
+        assert!(e.span() == Some(2..17));
+        let e = parser.parse_unit("kg;m").err().unwrap();
+        assert!(e.span() == Some(2..3));
     }
 
     #[test]
@@ -147,7 +583,7 @@ mod test {
             prop_assert!(result.is_ok());
             let unit = result.unwrap();
             prop_assert_eq!(&unit.name, &name);
-            prop_assert_eq!(unit.exp(), exp as f64);
+            prop_assert_eq!(unit.exp(), Rational::from(exp));
         }
     }
 
@@ -171,7 +607,7 @@ mod test {
 
             for i in 0..len {
                 prop_assert_eq!(&unit.partials[i].name, &names[i]);
-                prop_assert_eq!(unit.partials[i].exp(), exps[i] as f64);
+                prop_assert_eq!(unit.partials[i].exp(), Rational::from(exps[i]));
             }
         }
     }
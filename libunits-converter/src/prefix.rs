@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PrefixKind {
+    Si,
+    Binary,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Prefix {
+    pub name: &'static str,
+    pub symbols: &'static [&'static str],
+    pub factor: f64,
+    pub kind: PrefixKind,
+}
+
+const fn si(name: &'static str, symbols: &'static [&'static str], factor: f64) -> Prefix {
+    Prefix {
+        name,
+        symbols,
+        factor,
+        kind: PrefixKind::Si,
+    }
+}
+
+const fn binary(name: &'static str, symbols: &'static [&'static str], power: u32) -> Prefix {
+    Prefix {
+        name,
+        symbols,
+        factor: (1u128 << (10 * power)) as f64,
+        kind: PrefixKind::Binary,
+    }
+}
+
+pub const PREFIXES: [Prefix; 32] = [
+    si("quecto", &["q"], 1e-30),
+    si("ronto", &["r"], 1e-27),
+    si("yocto", &["y"], 1e-24),
+    si("zepto", &["z"], 1e-21),
+    si("atto", &["a"], 1e-18),
+    si("femto", &["f"], 1e-15),
+    si("pico", &["p"], 1e-12),
+    si("nano", &["n"], 1e-9),
+    si("micro", &["µ", "μ", "u"], 1e-6),
+    si("milli", &["m"], 1e-3),
+    si("centi", &["c"], 1e-2),
+    si("deci", &["d"], 1e-1),
+    si("deca", &["da"], 1e1),
+    si("hecto", &["h"], 1e2),
+    si("kilo", &["k"], 1e3),
+    si("mega", &["M"], 1e6),
+    si("giga", &["G"], 1e9),
+    si("tera", &["T"], 1e12),
+    si("peta", &["P"], 1e15),
+    si("exa", &["E"], 1e18),
+    si("zetta", &["Z"], 1e21),
+    si("yotta", &["Y"], 1e24),
+    si("ronna", &["R"], 1e27),
+    si("quetta", &["Q"], 1e30),
+    binary("kibi", &["Ki"], 1),
+    binary("mebi", &["Mi"], 2),
+    binary("gibi", &["Gi"], 3),
+    binary("tebi", &["Ti"], 4),
+    binary("pebi", &["Pi"], 5),
+    binary("exbi", &["Ei"], 6),
+    binary("zebi", &["Zi"], 7),
+    binary("yobi", &["Yi"], 8),
+];
+
+/// Every way `unit_name` can be read as a prefix (symbol or full name) followed by a non-empty
+/// base unit name. Whether the base exists and accepts the prefix is left to the caller.
+pub fn split_prefix(unit_name: &str) -> Vec<(&'static Prefix, &str)> {
+    let mut candidates = Vec::new();
+    for prefix in PREFIXES.iter() {
+        for start in prefix.symbols.iter().chain(std::iter::once(&prefix.name)) {
+            if let Some(base) = unit_name.strip_prefix(start)
+                && !base.is_empty()
+                && !candidates.contains(&(prefix, base))
+            {
+                candidates.push((prefix, base));
+            }
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_prefix() {
+        let names: Vec<(&str, &str)> = split_prefix("dam")
+            .into_iter()
+            .map(|(p, base)| (p.name, base))
+            .collect();
+        assert!(names == vec![("deci", "am"), ("deca", "m")]);
+
+        let names: Vec<(&str, &str)> = split_prefix("kilometer")
+            .into_iter()
+            .map(|(p, base)| (p.name, base))
+            .collect();
+        assert!(names == vec![("kilo", "ilometer"), ("kilo", "meter")]);
+
+        assert!(split_prefix("m").is_empty());
+        assert!(split_prefix("µm")[0].0.name == "micro");
+    }
+
+    #[test]
+    fn test_binary_factor() {
+        let kibi = PREFIXES.iter().find(|p| p.name == "kibi").unwrap();
+        assert!(kibi.factor == 1024.);
+        let exbi = PREFIXES.iter().find(|p| p.name == "exbi").unwrap();
+        assert!(exbi.factor == 2f64.powi(60));
+        let zebi = PREFIXES.iter().find(|p| p.name == "zebi").unwrap();
+        assert!(zebi.factor == 2f64.powi(70));
+        let yobi = PREFIXES.iter().find(|p| p.name == "yobi").unwrap();
+        assert!(yobi.factor == 2f64.powi(80));
+    }
+}
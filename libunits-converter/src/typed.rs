@@ -0,0 +1,467 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Quantities whose dimension is part of their type, checked by the compiler instead of
+//! [`UnitConverter::are_same_dimension`].
+//!
+//! A [`Quantity`] holds its value in SI units: adding a length to a duration does not compile,
+//! and a length divided by a duration is a [`Speed`]. Exponents are [`typenum`] integers, any
+//! product or quotient gets its dimension from the compiler, named or not. The catalogue is only
+//! needed where values enter or leave the typed world, [`Quantity::from_value`] and
+//! [`Quantity::to_value`] converting from and to any unit of the right dimension.
+//!
+//! ```
+//! use libunits_converter::typed::{Duration, Length, Quantity, Speed};
+//!
+//! let distance = Quantity::<Length>::new(3000.);
+//! let time = Quantity::<Duration>::new(1200.);
+//! let speed: Quantity<Speed> = distance / time;
+//! assert!(speed.si() == 2.5);
+//! ```
+//!
+//! ```compile_fail
+//! use libunits_converter::typed::{Duration, Length, Quantity};
+//!
+//! let _ = Quantity::<Length>::new(3000.) + Quantity::<Duration>::new(1200.);
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use typenum::{Diff, Integer, N1, N2, N3, P1, P2, P3, Sum, Z0};
+
+use crate::{
+    Dimension, ElementUnit, Rational, Unit, UnitConverter, UnitError, Value,
+    aio::AsyncUnitConverter,
+};
+
+/// Marker type of a dimension, see the types of this module.
+pub trait Dimensioned {
+    const DIMENSION: Dimension;
+    /// Name of the quantity, as in the `dimension` table when it has one.
+    const NAME: Option<&'static str>;
+}
+
+/// Dimension of exponents `M`, `T`, `L`, `Th`, `I`, `N` and `J`, the [`typenum`] integers of
+/// mass, duration, length, temperature, current, amount and luminosity. Only used as a type.
+pub struct Dim<M, T, L, Th, I, N, J>(PhantomData<(M, T, L, Th, I, N, J)>);
+
+impl<M, T, L, Th, I, N, J> Dimensioned for Dim<M, T, L, Th, I, N, J>
+where
+    M: Integer,
+    T: Integer,
+    L: Integer,
+    Th: Integer,
+    I: Integer,
+    N: Integer,
+    J: Integer,
+{
+    const DIMENSION: Dimension = dimension(Self::EXPONENTS);
+    const NAME: Option<&'static str> = name(Self::EXPONENTS);
+}
+
+impl<M, T, L, Th, I, N, J> Dim<M, T, L, Th, I, N, J>
+where
+    M: Integer,
+    T: Integer,
+    L: Integer,
+    Th: Integer,
+    I: Integer,
+    N: Integer,
+    J: Integer,
+{
+    const EXPONENTS: [i32; 7] = [M::I32, T::I32, L::I32, Th::I32, I::I32, N::I32, J::I32];
+}
+
+/// SI base unit of each exponent of a [`Dimension`], in the same order.
+const BASE_UNITS: [&str; 7] = ["kg", "s", "m", "K", "A", "mol", "cd"];
+
+const fn dimension(exponents: [i32; 7]) -> Dimension {
+    let mut dimension = [Rational::ZERO; 7];
+    let mut i = 0;
+    while i < 7 {
+        dimension[i] = Rational::new(exponents[i], 1);
+        i += 1;
+    }
+    Dimension(dimension)
+}
+
+/// Quantity of [`NAMES`] with these exponents.
+const fn name(exponents: [i32; 7]) -> Option<&'static str> {
+    let mut i = 0;
+    while i < NAMES.len() {
+        let (name, named) = NAMES[i];
+        let mut j = 0;
+        while j < 7 && named[j] == exponents[j] {
+            j += 1;
+        }
+        if j == 7 {
+            return Some(name);
+        }
+        i += 1;
+    }
+    None
+}
+
+macro_rules! dimensions {
+    ($($(#[$doc:meta])* $name:ident = $quantity:literal [$($exp:ident),*];)*) => {
+        $(
+            $(#[$doc])*
+            pub type $name = Dim<$($exp),*>;
+        )*
+
+        const NAMES: &[(&str, [i32; 7])] = &[$(($quantity, [$($exp::I32),*])),*];
+    };
+}
+
+// Exponents of mass, duration, length, temperature, current, amount and luminosity
+dimensions! {
+    /// Pure number, ratio of two quantities of the same dimension.
+    Dimensionless = "dimensionless" [Z0, Z0, Z0, Z0, Z0, Z0, Z0];
+    Mass = "mass" [P1, Z0, Z0, Z0, Z0, Z0, Z0];
+    Duration = "duration" [Z0, P1, Z0, Z0, Z0, Z0, Z0];
+    Length = "length" [Z0, Z0, P1, Z0, Z0, Z0, Z0];
+    Temperature = "temperature" [Z0, Z0, Z0, P1, Z0, Z0, Z0];
+    Current = "current" [Z0, Z0, Z0, Z0, P1, Z0, Z0];
+    Amount = "sub_amount" [Z0, Z0, Z0, Z0, Z0, P1, Z0];
+    Luminosity = "luminosity" [Z0, Z0, Z0, Z0, Z0, Z0, P1];
+    Area = "area" [Z0, Z0, P2, Z0, Z0, Z0, Z0];
+    Volume = "volume" [Z0, Z0, P3, Z0, Z0, Z0, Z0];
+    Frequency = "frequency" [Z0, N1, Z0, Z0, Z0, Z0, Z0];
+    Speed = "speed" [Z0, N1, P1, Z0, Z0, Z0, Z0];
+    Acceleration = "acceleration" [Z0, N2, P1, Z0, Z0, Z0, Z0];
+    Density = "density" [P1, Z0, N3, Z0, Z0, Z0, Z0];
+    Force = "force" [P1, N2, P1, Z0, Z0, Z0, Z0];
+    Pressure = "pressure" [P1, N2, N1, Z0, Z0, Z0, Z0];
+    /// Also the dimension of a torque.
+    Energy = "energy" [P1, N2, P2, Z0, Z0, Z0, Z0];
+    Power = "power" [P1, N3, P2, Z0, Z0, Z0, Z0];
+    Charge = "charge" [Z0, P1, Z0, Z0, P1, Z0, Z0];
+    Voltage = "voltage" [P1, N3, P2, Z0, N1, Z0, Z0];
+    Resistance = "resistance" [P1, N3, P2, Z0, N2, Z0, Z0];
+}
+
+/// Value of dimension `D` in SI units.
+pub struct Quantity<D> {
+    si: f64,
+    dimension: PhantomData<D>,
+}
+
+impl<D: Dimensioned> Quantity<D> {
+    /// Quantity of `si` SI units of `D` (metres for a [`Length`], pascals for a [`Pressure`]).
+    pub const fn new(si: f64) -> Self {
+        Self {
+            si,
+            dimension: PhantomData,
+        }
+    }
+
+    /// Value in SI units.
+    pub const fn si(&self) -> f64 {
+        self.si
+    }
+
+    /// Product of SI base units of dimension `D`, `kg*m^2*s^-2` for an [`Energy`].
+    pub fn si_unit() -> Unit {
+        let partials = BASE_UNITS
+            .iter()
+            .zip(D::DIMENSION.0)
+            .map(|(name, exp)| ElementUnit::new(name, exp))
+            .collect();
+        // Base units are distinct, nothing is merged
+        Unit::from_vec(partials).simplify().unwrap()
+    }
+
+    /// Same value as an untyped [`Value`] in SI base units, no catalogue needed.
+    pub fn into_value(self) -> Value {
+        Value::from_value(Self::si_unit(), self.si)
+    }
+
+    fn check(dimension: Dimension) -> Result<(), UnitError> {
+        match dimension == D::DIMENSION {
+            true => Ok(()),
+            false => Err(UnitError::BadDimension(dimension, D::DIMENSION)),
+        }
+    }
+
+    /// Reads `value` through `converter`, failing with [`UnitError::BadDimension`] when its unit
+    /// is not of dimension `D`.
+    pub fn from_value<C: UnitConverter>(converter: &C, value: &Value) -> Result<Self, UnitError> {
+        Self::check(converter.get_dimension(&value.unit)?)?;
+        let factor = converter.get_conversion_factor(&value.unit)?;
+        let offset = converter.get_conversion_offset(&value.unit)?;
+        Ok(Self::new(value.value * factor + offset))
+    }
+
+    /// See [`Quantity::from_value`].
+    pub async fn from_value_async<C: AsyncUnitConverter>(
+        converter: &C,
+        value: &Value,
+    ) -> Result<Self, UnitError> {
+        Self::check(converter.get_dimension(&value.unit).await?)?;
+        let factor = converter.get_conversion_factor(&value.unit).await?;
+        let offset = converter.get_conversion_offset(&value.unit).await?;
+        Ok(Self::new(value.value * factor + offset))
+    }
+
+    /// The quantity in `unit` through `converter`, failing with [`UnitError::BadDimension`] when
+    /// `unit` is not of dimension `D`.
+    pub fn to_value<C: UnitConverter>(
+        &self,
+        converter: &C,
+        unit: &Unit,
+    ) -> Result<Value, UnitError> {
+        Self::check(converter.get_dimension(unit)?)?;
+        let factor = converter.get_conversion_factor(unit)?;
+        let offset = converter.get_conversion_offset(unit)?;
+        Ok(Value::from_value(unit.clone(), (self.si - offset) / factor))
+    }
+
+    /// See [`Quantity::to_value`].
+    pub async fn to_value_async<C: AsyncUnitConverter>(
+        &self,
+        converter: &C,
+        unit: &Unit,
+    ) -> Result<Value, UnitError> {
+        Self::check(converter.get_dimension(unit).await?)?;
+        let factor = converter.get_conversion_factor(unit).await?;
+        let offset = converter.get_conversion_offset(unit).await?;
+        Ok(Value::from_value(unit.clone(), (self.si - offset) / factor))
+    }
+}
+
+// Derived impls would require `D` itself to implement the traits
+impl<D> Clone for Quantity<D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for Quantity<D> {}
+
+impl<D> PartialEq for Quantity<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.si == other.si
+    }
+}
+
+impl<D> PartialOrd for Quantity<D> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.si.partial_cmp(&other.si)
+    }
+}
+
+impl<D: Dimensioned> fmt::Debug for Quantity<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match D::NAME {
+            Some(name) => write!(f, "Quantity<{}>({})", name, self.si),
+            None => write!(f, "Quantity<{}>({})", D::DIMENSION, self.si),
+        }
+    }
+}
+
+/// The SI value followed by the SI base units, `{:.2}` and `{:#}` as for [`Value`].
+impl<D: Dimensioned> fmt::Display for Quantity<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.into_value(), f)
+    }
+}
+
+impl<D> Add for Quantity<D> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            si: self.si + rhs.si,
+            dimension: PhantomData,
+        }
+    }
+}
+
+impl<D> Sub for Quantity<D> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            si: self.si - rhs.si,
+            dimension: PhantomData,
+        }
+    }
+}
+
+impl<D> AddAssign for Quantity<D> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.si += rhs.si;
+    }
+}
+
+impl<D> SubAssign for Quantity<D> {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.si -= rhs.si;
+    }
+}
+
+impl<D> Neg for Quantity<D> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            si: -self.si,
+            dimension: PhantomData,
+        }
+    }
+}
+
+impl<D> Mul<f64> for Quantity<D> {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self {
+            si: self.si * rhs,
+            dimension: PhantomData,
+        }
+    }
+}
+
+impl<D> Mul<Quantity<D>> for f64 {
+    type Output = Quantity<D>;
+
+    fn mul(self, rhs: Quantity<D>) -> Quantity<D> {
+        rhs * self
+    }
+}
+
+impl<D> Div<f64> for Quantity<D> {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self {
+        Self {
+            si: self.si / rhs,
+            dimension: PhantomData,
+        }
+    }
+}
+
+/// Ratio of two quantities of the same dimension.
+impl From<Quantity<Dimensionless>> for f64 {
+    fn from(ratio: Quantity<Dimensionless>) -> f64 {
+        ratio.si
+    }
+}
+
+/// Product or quotient of quantities, exponents added or subtracted by the compiler.
+macro_rules! products {
+    ($($trait:ident $method:ident $op:tt $exp:ident $out:ident;)*) => {$(
+        impl<Ma, Ta, La, Tha, Ia, Na, Ja, Mb, Tb, Lb, Thb, Ib, Nb, Jb>
+            $trait<Quantity<Dim<Mb, Tb, Lb, Thb, Ib, Nb, Jb>>>
+            for Quantity<Dim<Ma, Ta, La, Tha, Ia, Na, Ja>>
+        where
+            Ma: $exp<Mb>,
+            Ta: $exp<Tb>,
+            La: $exp<Lb>,
+            Tha: $exp<Thb>,
+            Ia: $exp<Ib>,
+            Na: $exp<Nb>,
+            Ja: $exp<Jb>,
+        {
+            type Output = Quantity<
+                Dim<
+                    $out<Ma, Mb>,
+                    $out<Ta, Tb>,
+                    $out<La, Lb>,
+                    $out<Tha, Thb>,
+                    $out<Ia, Ib>,
+                    $out<Na, Nb>,
+                    $out<Ja, Jb>,
+                >,
+            >;
+
+            fn $method(self, rhs: Quantity<Dim<Mb, Tb, Lb, Thb, Ib, Nb, Jb>>) -> Self::Output {
+                Quantity {
+                    si: self.si $op rhs.si,
+                    dimension: PhantomData,
+                }
+            }
+        }
+    )*};
+}
+
+products! {
+    Mul mul * Add Sum;
+    Div div / Sub Diff;
+}
+
+#[cfg(all(test, feature = "embedded"))]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{InlineUnitParser, MainConverter, UnitParser, unitquery::MemoryUnitQuery};
+
+    #[test]
+    fn test_quantity_arithmetic() {
+        let distance = Quantity::<Length>::new(3000.);
+        let time = Quantity::<Duration>::new(1200.);
+        let speed = distance / time;
+        assert!(speed == Quantity::<Speed>::new(2.5));
+        assert!(speed * time == distance);
+        assert!(f64::from(distance / Quantity::<Length>::new(1500.)) == 2.);
+
+        let force = Quantity::<Mass>::new(2.) * (speed / Quantity::<Duration>::new(0.5));
+        assert!(force.si() == 10.);
+        let energy: Quantity<Energy> = force * Quantity::<Length>::new(3.);
+        assert!(energy.si() == 30.);
+        assert!(-energy + 2. * energy == energy);
+
+        // Any product has a dimension, named or not
+        let momentum = Quantity::<Mass>::new(2.) * speed;
+        assert!(format!("{:?}", momentum) == "Quantity<[1, -1, 1, 0, 0, 0, 0]>(5)");
+        let _: Quantity<Length> = energy / force;
+        let _: Quantity<Current> = Quantity::<Power>::new(6.) / Quantity::<Voltage>::new(3.);
+        let squared = speed * speed;
+        assert!(squared.si() == 6.25);
+        assert!(format!("{:?}", energy) == "Quantity<energy>(30)");
+
+        for (dimension, size) in [(Energy::DIMENSION, 3), (Dimensionless::DIMENSION, 0)] {
+            assert!(dimension.0.iter().filter(|e| !e.is_zero()).count() == size);
+        }
+        assert!(Quantity::<Energy>::si_unit().to_string() == "kg*m^2*s^-2");
+        assert!(energy.to_string() == "30 kg*m^2*s^-2");
+    }
+
+    #[tokio::test]
+    async fn test_quantity_conversion() {
+        let c = Arc::new(MemoryUnitQuery::embedded().await.unwrap());
+        let converter = MainConverter::new(c);
+        let parser = InlineUnitParser::default();
+
+        let pressure = Value::from_value(parser.parse_unit("bar").unwrap(), 5.);
+        let pressure = Quantity::<Pressure>::from_value(&converter, &pressure).unwrap();
+        let volume = Value::from_value(parser.parse_unit("L").unwrap(), 2.);
+        let volume = Quantity::<Volume>::from_value_async(&converter, &volume)
+            .await
+            .unwrap();
+        let energy = pressure * volume;
+        let kj = energy
+            .to_value(&converter, &parser.parse_unit("kJ").unwrap())
+            .unwrap();
+        assert!((kj.value - 1.).abs() < 1e-12);
+
+        // Round trip through the SI base units
+        let back = Quantity::<Energy>::from_value(&converter, &energy.into_value()).unwrap();
+        assert!(back == energy);
+
+        // Offsets of absolute temperatures are applied
+        let celsius = Value::from_value(parser.parse_unit("degC").unwrap(), 20.);
+        let kelvin = Quantity::<Temperature>::from_value(&converter, &celsius).unwrap();
+        assert!((kelvin.si() - 293.15).abs() < 1e-9);
+
+        let e = Quantity::<Length>::from_value(&converter, &celsius)
+            .err()
+            .unwrap();
+        assert!(matches!(e, UnitError::BadDimension(..)));
+        let metre = parser.parse_unit("m").unwrap();
+        let e = volume.to_value_async(&converter, &metre).await;
+        assert!(matches!(e, Err(UnitError::BadDimension(..))));
+    }
+}
@@ -0,0 +1,426 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::{
+    UnitError,
+    datatypes::{Dimension, ElementUnit},
+    prefix::PrefixKind,
+};
+
+use super::{AsyncUnitQuery, UnitQuery};
+
+/// Memoises the answers of another query, so a unit is looked up once however many values are
+/// converted to or from it. Failed lookups are not kept.
+///
+/// The cache is dropped when the [revision](UnitQuery::revision) of the inner query moves, which
+/// is checked at most once per [`CachedUnitQuery::CHECK_INTERVAL`], or on [`invalidate`].
+///
+/// [`invalidate`]: CachedUnitQuery::invalidate
+pub struct CachedUnitQuery<Q> {
+    inner: Q,
+    cache: Cache,
+    /// Last revision check and the revision seen then.
+    checked: Mutex<(Instant, u64)>,
+}
+
+#[derive(Default)]
+struct Cache {
+    unit_list: RwLock<Option<Vec<String>>>,
+    factors: RwLock<HashMap<String, f64>>,
+    offsets: RwLock<HashMap<String, f64>>,
+    dimension_names: RwLock<HashMap<String, String>>,
+    /// By dimension name.
+    dimensions: RwLock<HashMap<String, Dimension>>,
+    prefixes: RwLock<HashMap<(String, PrefixKind), bool>>,
+    definitions: RwLock<HashMap<String, Option<String>>>,
+    aliases: RwLock<Option<Vec<(String, String)>>>,
+    dimension_list: RwLock<Option<Vec<(String, Dimension)>>>,
+}
+
+impl Cache {
+    fn clear(&self) {
+        *self.unit_list.write().unwrap() = None;
+        self.factors.write().unwrap().clear();
+        self.offsets.write().unwrap().clear();
+        self.dimension_names.write().unwrap().clear();
+        self.dimensions.write().unwrap().clear();
+        self.prefixes.write().unwrap().clear();
+        self.definitions.write().unwrap().clear();
+        *self.aliases.write().unwrap() = None;
+        *self.dimension_list.write().unwrap() = None;
+    }
+}
+
+fn cached<K: Eq + Hash, V: Clone>(map: &RwLock<HashMap<K, V>>, key: &K) -> Option<V> {
+    map.read().unwrap().get(key).cloned()
+}
+
+fn store<K: Eq + Hash, V: Clone>(map: &RwLock<HashMap<K, V>>, key: K, value: &V) {
+    map.write().unwrap().insert(key, value.clone());
+}
+
+impl<Q> CachedUnitQuery<Q> {
+    pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn inner(&self) -> &Q {
+        &self.inner
+    }
+
+    /// Forgets every cached answer.
+    pub fn invalidate(&self) {
+        self.cache.clear();
+    }
+
+    fn check(&self, revision: impl FnOnce(&Q) -> u64) {
+        let mut checked = self.checked.lock().unwrap();
+        if checked.0.elapsed() < Self::CHECK_INTERVAL {
+            return;
+        }
+        let revision = revision(&self.inner);
+        if revision != checked.1 {
+            self.cache.clear();
+        }
+        *checked = (Instant::now(), revision);
+    }
+}
+
+impl<Q: UnitQuery> CachedUnitQuery<Q> {
+    pub fn new(inner: Q) -> Self {
+        let revision = inner.revision();
+        Self {
+            inner,
+            cache: Cache::default(),
+            checked: Mutex::new((Instant::now(), revision)),
+        }
+    }
+
+    fn dimension_key(&self, unit: &ElementUnit) -> Result<String, UnitError> {
+        match &unit.dim {
+            Some(name) => Ok(name.to_owned()),
+            None => UnitQuery::get_dimension_name(self, unit),
+        }
+    }
+}
+
+impl<Q: UnitQuery> UnitQuery for CachedUnitQuery<Q> {
+    fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        self.check(Q::revision);
+        if let Some(factor) = cached(&self.cache.factors, &unit.name) {
+            return Ok(factor);
+        }
+        let factor = self.inner.get_conversion_factor(unit)?;
+        store(&self.cache.factors, unit.name.clone(), &factor);
+        Ok(factor)
+    }
+
+    fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        self.check(Q::revision);
+        if let Some(offset) = cached(&self.cache.offsets, &unit.name) {
+            return Ok(offset);
+        }
+        let offset = self.inner.get_conversion_offset(unit)?;
+        store(&self.cache.offsets, unit.name.clone(), &offset);
+        Ok(offset)
+    }
+
+    fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+        self.check(Q::revision);
+        if let Some(names) = self.cache.unit_list.read().unwrap().as_ref() {
+            return Ok(names.clone());
+        }
+        let names = self.inner.get_unit_list()?;
+        *self.cache.unit_list.write().unwrap() = Some(names.clone());
+        Ok(names)
+    }
+
+    fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+        self.check(Q::revision);
+        let dim_name = self.dimension_key(unit)?;
+        if let Some(dimension) = cached(&self.cache.dimensions, &dim_name) {
+            return Ok((dim_name, dimension));
+        }
+        let mut unit = unit.clone();
+        unit.set_dim(&dim_name);
+        let (dim_name, dimension) = self.inner.get_dimension(&unit)?;
+        store(&self.cache.dimensions, dim_name.clone(), &dimension);
+        Ok((dim_name, dimension))
+    }
+
+    fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
+        self.check(Q::revision);
+        if let Some(name) = cached(&self.cache.dimension_names, &p_unit.name) {
+            return Ok(name);
+        }
+        let name = self.inner.get_dimension_name(p_unit)?;
+        store(&self.cache.dimension_names, p_unit.name.clone(), &name);
+        Ok(name)
+    }
+
+    fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError> {
+        self.check(Q::revision);
+        let key = (unit_name.to_owned(), kind);
+        if let Some(accepted) = cached(&self.cache.prefixes, &key) {
+            return Ok(accepted);
+        }
+        let accepted = self.inner.accepts_prefix(unit_name, kind)?;
+        store(&self.cache.prefixes, key, &accepted);
+        Ok(accepted)
+    }
+
+    fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+        self.check(Q::revision);
+        if let Some(definition) = cached(&self.cache.definitions, &unit.name) {
+            return Ok(definition);
+        }
+        let definition = self.inner.get_definition(unit)?;
+        store(&self.cache.definitions, unit.name.clone(), &definition);
+        Ok(definition)
+    }
+
+    fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        self.check(Q::revision);
+        if let Some(aliases) = self.cache.aliases.read().unwrap().as_ref() {
+            return Ok(aliases.clone());
+        }
+        let aliases = self.inner.get_aliases()?;
+        *self.cache.aliases.write().unwrap() = Some(aliases.clone());
+        Ok(aliases)
+    }
+
+    fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        self.check(Q::revision);
+        if let Some(dimensions) = self.cache.dimension_list.read().unwrap().as_ref() {
+            return Ok(dimensions.clone());
+        }
+        let dimensions = self.inner.get_dimensions()?;
+        *self.cache.dimension_list.write().unwrap() = Some(dimensions.clone());
+        Ok(dimensions)
+    }
+
+    fn revision(&self) -> u64 {
+        self.inner.revision()
+    }
+}
+
+impl<Q: AsyncUnitQuery> AsyncUnitQuery for CachedUnitQuery<Q> {
+    async fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        self.check(Q::revision);
+        if let Some(factor) = cached(&self.cache.factors, &unit.name) {
+            return Ok(factor);
+        }
+        let factor = self.inner.get_conversion_factor(unit).await?;
+        store(&self.cache.factors, unit.name.clone(), &factor);
+        Ok(factor)
+    }
+
+    async fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        self.check(Q::revision);
+        if let Some(offset) = cached(&self.cache.offsets, &unit.name) {
+            return Ok(offset);
+        }
+        let offset = self.inner.get_conversion_offset(unit).await?;
+        store(&self.cache.offsets, unit.name.clone(), &offset);
+        Ok(offset)
+    }
+
+    async fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+        self.check(Q::revision);
+        if let Some(names) = self.cache.unit_list.read().unwrap().as_ref() {
+            return Ok(names.clone());
+        }
+        let names = self.inner.get_unit_list().await?;
+        *self.cache.unit_list.write().unwrap() = Some(names.clone());
+        Ok(names)
+    }
+
+    async fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+        self.check(Q::revision);
+        let dim_name = match &unit.dim {
+            Some(name) => name.to_owned(),
+            None => AsyncUnitQuery::get_dimension_name(self, unit).await?,
+        };
+        if let Some(dimension) = cached(&self.cache.dimensions, &dim_name) {
+            return Ok((dim_name, dimension));
+        }
+        let mut unit = unit.clone();
+        unit.set_dim(&dim_name);
+        let (dim_name, dimension) = self.inner.get_dimension(&unit).await?;
+        store(&self.cache.dimensions, dim_name.clone(), &dimension);
+        Ok((dim_name, dimension))
+    }
+
+    async fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
+        self.check(Q::revision);
+        if let Some(name) = cached(&self.cache.dimension_names, &p_unit.name) {
+            return Ok(name);
+        }
+        let name = self.inner.get_dimension_name(p_unit).await?;
+        store(&self.cache.dimension_names, p_unit.name.clone(), &name);
+        Ok(name)
+    }
+
+    async fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError> {
+        self.check(Q::revision);
+        let key = (unit_name.to_owned(), kind);
+        if let Some(accepted) = cached(&self.cache.prefixes, &key) {
+            return Ok(accepted);
+        }
+        let accepted = self.inner.accepts_prefix(unit_name, kind).await?;
+        store(&self.cache.prefixes, key, &accepted);
+        Ok(accepted)
+    }
+
+    async fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+        self.check(Q::revision);
+        if let Some(definition) = cached(&self.cache.definitions, &unit.name) {
+            return Ok(definition);
+        }
+        let definition = self.inner.get_definition(unit).await?;
+        store(&self.cache.definitions, unit.name.clone(), &definition);
+        Ok(definition)
+    }
+
+    async fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        self.check(Q::revision);
+        if let Some(aliases) = self.cache.aliases.read().unwrap().as_ref() {
+            return Ok(aliases.clone());
+        }
+        let aliases = self.inner.get_aliases().await?;
+        *self.cache.aliases.write().unwrap() = Some(aliases.clone());
+        Ok(aliases)
+    }
+
+    async fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        self.check(Q::revision);
+        if let Some(dimensions) = self.cache.dimension_list.read().unwrap().as_ref() {
+            return Ok(dimensions.clone());
+        }
+        let dimensions = self.inner.get_dimensions().await?;
+        *self.cache.dimension_list.write().unwrap() = Some(dimensions.clone());
+        Ok(dimensions)
+    }
+
+    fn revision(&self) -> u64 {
+        self.inner.revision()
+    }
+}
+
+#[cfg(all(test, feature = "embedded"))]
+mod test {
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::unitquery::MemoryUnitQuery;
+
+    /// Counts the lookups reaching the catalogue.
+    struct Counting {
+        inner: MemoryUnitQuery,
+        calls: AtomicUsize,
+        revision: AtomicU64,
+    }
+
+    impl UnitQuery for Counting {
+        fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            UnitQuery::get_conversion_factor(&self.inner, unit)
+        }
+        fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            UnitQuery::get_conversion_offset(&self.inner, unit)
+        }
+        fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            UnitQuery::get_unit_list(&self.inner)
+        }
+        fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            UnitQuery::get_dimension(&self.inner, unit)
+        }
+        fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            UnitQuery::get_dimension_name(&self.inner, p_unit)
+        }
+        fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            UnitQuery::accepts_prefix(&self.inner, unit_name, kind)
+        }
+        fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            UnitQuery::get_definition(&self.inner, unit)
+        }
+        fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            UnitQuery::get_aliases(&self.inner)
+        }
+        fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            UnitQuery::get_dimensions(&self.inner)
+        }
+        fn revision(&self) -> u64 {
+            self.revision.load(Ordering::Relaxed)
+        }
+    }
+
+    async fn counting() -> CachedUnitQuery<Counting> {
+        CachedUnitQuery::new(Counting {
+            inner: MemoryUnitQuery::embedded().await.unwrap(),
+            calls: AtomicUsize::new(0),
+            revision: AtomicU64::new(0),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_memoised() {
+        let c = counting().await;
+        let pu = ElementUnit::new("km", 1);
+        for _ in 0..10 {
+            assert!(c.get_conversion_factor(&pu).unwrap() == 1e3);
+            assert!(c.get_dimension(&pu).unwrap().0 == *"length");
+        }
+        let calls = c.inner().calls.load(Ordering::Relaxed);
+        assert!(calls == 3);
+
+        // Errors are not cached and still reported
+        assert!(
+            c.get_conversion_factor(&ElementUnit::new("FALSEUNIT", 1))
+                .is_err()
+        );
+        assert!(
+            c.get_conversion_factor(&ElementUnit::new("FALSEUNIT", 1))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalidate() {
+        let c = counting().await;
+        let pu = ElementUnit::new("g", 1);
+        c.get_conversion_factor(&pu).unwrap();
+        c.invalidate();
+        c.get_conversion_factor(&pu).unwrap();
+        assert!(c.inner().calls.load(Ordering::Relaxed) == 2);
+
+        // A revision change is picked up once the check interval has elapsed
+        c.inner().revision.store(1, Ordering::Relaxed);
+        c.checked.lock().unwrap().0 -= CachedUnitQuery::<Counting>::CHECK_INTERVAL;
+        c.get_conversion_factor(&pu).unwrap();
+        assert!(c.inner().calls.load(Ordering::Relaxed) == 3);
+        c.get_conversion_factor(&pu).unwrap();
+        assert!(c.inner().calls.load(Ordering::Relaxed) == 3);
+
+        // Also when the dimension name is already known and only the dimension is looked up
+        let mut pu = ElementUnit::new("m", 1);
+        pu.set_dim("length");
+        c.get_dimension(&pu).unwrap();
+        c.get_dimension(&pu).unwrap();
+        assert!(c.inner().calls.load(Ordering::Relaxed) == 4);
+        c.inner().revision.store(2, Ordering::Relaxed);
+        c.checked.lock().unwrap().0 -= CachedUnitQuery::<Counting>::CHECK_INTERVAL;
+        c.get_dimension(&pu).unwrap();
+        assert!(c.inner().calls.load(Ordering::Relaxed) == 5);
+    }
+}
@@ -0,0 +1,337 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use crate::{
+    UnitError,
+    datatypes::{Dimension, ElementUnit},
+    prefix::PrefixKind,
+};
+
+use super::sql::{DIMENSION_COLUMNS, as_number, dimension_from_row, sql_error};
+use super::{AsyncUnitQuery, UnitQuery, lookup, prefixed_definition};
+
+struct UnitRow {
+    dimension_name: Option<String>,
+    factor: f64,
+    offset: f64,
+    si_prefix: bool,
+    binary_prefix: bool,
+    definition: Option<String>,
+}
+
+/// Whole catalogue held in memory: once built, queries never touch a database.
+pub struct MemoryUnitQuery {
+    names: Vec<String>,
+    units: HashMap<String, UnitRow>,
+    dimensions: HashMap<String, Dimension>,
+    /// Dimension names in table order.
+    dimension_names: Vec<String>,
+    /// Alias with the unit it names, in table order.
+    aliases: Vec<(String, String)>,
+    /// Unit named by each alias.
+    alias_units: HashMap<String, String>,
+}
+
+impl MemoryUnitQuery {
+    /// Catalogue built from the migrations compiled into the crate, no file needed.
+    #[cfg(feature = "embedded")]
+    pub async fn embedded() -> Result<Self, UnitError> {
+        use crate::migrations::{embedded, migrate};
+
+        let db = turso::Builder::new_local(":memory:")
+            .build()
+            .await
+            .map_err(sql_error)?;
+        let conn = db.connect().map_err(sql_error)?;
+        migrate(&conn, &embedded()).await?;
+        Self::load(&conn).await
+    }
+
+    /// Copies the `conversiontable`, `dimension` and `alias` tables behind `conn`.
+    pub async fn load(conn: &turso::Connection) -> Result<Self, UnitError> {
+        let mut query = Self {
+            names: Vec::new(),
+            units: HashMap::new(),
+            dimensions: HashMap::new(),
+            dimension_names: Vec::new(),
+            aliases: Vec::new(),
+            alias_units: HashMap::new(),
+        };
+
+        let mut rows = conn
+            .query(
+                "SELECT unit_name, dimension_name, conversionfactor, conversionoffset, si_prefix, binary_prefix,
+                definition FROM conversiontable ORDER BY id",
+                (),
+            )
+            .await
+            .map_err(sql_error)?;
+        while let Some(row) = rows.next().await.map_err(sql_error)? {
+            let text = |i| {
+                row.get_value(i)
+                    .ok()
+                    .and_then(|v| v.as_text().cloned())
+                    .ok_or_else(|| UnitError::Query(format!("SqlQuery: column {} is not text", i)))
+            };
+            let number = |i| {
+                row.get_value(i).ok().and_then(as_number).ok_or_else(|| {
+                    UnitError::Query(format!("SqlQuery: column {} is not a number", i))
+                })
+            };
+            let name = text(0)?;
+            let unit = UnitRow {
+                dimension_name: text(1).ok(),
+                factor: number(2)?,
+                offset: number(3)?,
+                si_prefix: number(4)? != 0.,
+                binary_prefix: number(5)? != 0.,
+                definition: text(6).ok(),
+            };
+            if query.units.insert(name.clone(), unit).is_some() {
+                return Err(UnitError::Query(format!(
+                    "SqlQuery: unit {} is defined twice",
+                    name
+                )));
+            }
+            query.names.push(name);
+        }
+
+        let mut rows = conn
+            .query(
+                &format!(
+                    "SELECT {} FROM dimension ORDER BY iddimension",
+                    DIMENSION_COLUMNS
+                ),
+                (),
+            )
+            .await
+            .map_err(sql_error)?;
+        while let Some(row) = rows.next().await.map_err(sql_error)? {
+            let (name, dimension) = dimension_from_row(&row)?;
+            query.dimensions.insert(name.clone(), dimension);
+            query.dimension_names.push(name);
+        }
+
+        let mut rows = conn
+            .query("SELECT alias_name, unit_name FROM alias ORDER BY id", ())
+            .await
+            .map_err(sql_error)?;
+        while let Some(row) = rows.next().await.map_err(sql_error)? {
+            let text = |i| {
+                row.get_value(i)
+                    .ok()
+                    .and_then(|v| v.as_text().cloned())
+                    .ok_or_else(|| UnitError::Query(format!("SqlQuery: column {} is not text", i)))
+            };
+            let (alias, unit) = (text(0)?, text(1)?);
+            query.alias_units.insert(alias.clone(), unit.clone());
+            query.aliases.push((alias, unit));
+        }
+
+        Ok(query)
+    }
+
+    /// Row named `unit_name` directly or through an alias, with its catalogue name.
+    fn entry(&self, unit_name: &str) -> Option<(&String, &UnitRow)> {
+        self.units.get_key_value(unit_name).or_else(|| {
+            let unit = self.alias_units.get(unit_name)?;
+            self.units.get_key_value(unit)
+        })
+    }
+
+    fn unit(&self, unit_name: &str) -> Result<Option<&UnitRow>, UnitError> {
+        Ok(self.entry(unit_name).map(|(_, row)| row))
+    }
+}
+
+impl UnitQuery for MemoryUnitQuery {
+    fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        let (prefix, row) = lookup(self, &unit.name, |name| self.unit(name))?;
+        Ok(prefix.map_or(row.factor, |prefix| prefix.factor * row.factor))
+    }
+
+    fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        lookup(self, &unit.name, |name| self.unit(name)).map(|(_, row)| row.offset)
+    }
+
+    fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+        if self.names.is_empty() {
+            Err(UnitError::Custom("Empty databse".to_owned()))
+        } else {
+            Ok(self.names.clone())
+        }
+    }
+
+    fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+        let dim_name = match &unit.dim {
+            Some(name) => name.to_owned(),
+            None => UnitQuery::get_dimension_name(self, unit)?,
+        };
+        match self.dimensions.get(&dim_name) {
+            Some(dimension) => Ok((dim_name, *dimension)),
+            None => Err(UnitError::NoDimension {
+                dimension: dim_name,
+            }),
+        }
+    }
+
+    fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
+        let (_, row) = lookup(self, &p_unit.name, |name| self.unit(name))?;
+        row.dimension_name
+            .clone()
+            .ok_or_else(|| UnitError::NoStoredDimension {
+                unit: p_unit.name.clone(),
+            })
+    }
+
+    fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError> {
+        Ok(self.entry(unit_name).is_some_and(|(_, row)| match kind {
+            PrefixKind::Si => row.si_prefix,
+            PrefixKind::Binary => row.binary_prefix,
+        }))
+    }
+
+    fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+        let units = |name: &str| Ok(self.entry(name));
+        Ok(match lookup(self, &unit.name, units)? {
+            (None, (_, row)) => row.definition.clone(),
+            (Some(prefix), (base, _)) => Some(prefixed_definition(prefix, base)),
+        })
+    }
+
+    fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        Ok(self.aliases.clone())
+    }
+
+    fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        Ok(self
+            .dimension_names
+            .iter()
+            .map(|name| (name.clone(), self.dimensions[name]))
+            .collect())
+    }
+}
+
+/// Nothing to wait for, answers right away.
+impl AsyncUnitQuery for MemoryUnitQuery {
+    async fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        UnitQuery::get_conversion_factor(self, unit)
+    }
+
+    async fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        UnitQuery::get_conversion_offset(self, unit)
+    }
+
+    async fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+        UnitQuery::get_unit_list(self)
+    }
+
+    async fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+        UnitQuery::get_dimension(self, unit)
+    }
+
+    async fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
+        UnitQuery::get_dimension_name(self, p_unit)
+    }
+
+    async fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError> {
+        UnitQuery::accepts_prefix(self, unit_name, kind)
+    }
+
+    async fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+        UnitQuery::get_definition(self, unit)
+    }
+
+    async fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        UnitQuery::get_aliases(self)
+    }
+
+    async fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        UnitQuery::get_dimensions(self)
+    }
+}
+
+#[cfg(all(test, feature = "embedded"))]
+mod test {
+    use super::{Dimension, ElementUnit, MemoryUnitQuery, UnitQuery};
+    use crate::{Rational, UnitError};
+
+    #[tokio::test]
+    async fn test_embedded_catalogue() {
+        let c = MemoryUnitQuery::embedded().await.unwrap();
+        assert!(c.get_unit_list().unwrap()[..3] == ["kg", "g", "t"]);
+
+        let pu = ElementUnit::new("g", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 1e-3);
+        assert!(c.get_dimension_name(&pu).unwrap() == *"mass");
+
+        let pu = ElementUnit::new("v", 1);
+        let (name, dim) = c.get_dimension(&pu).unwrap();
+        assert!(name == *"voltage");
+        assert!(dim == Dimension::from([1, -3, 2, 0, -1, 0, 0]));
+        assert!(dim.0[1] == Rational::from(-3));
+
+        let pu = ElementUnit::new("degF", 1);
+        assert!(c.get_conversion_offset(&pu).unwrap() > 255.);
+
+        let pu = ElementUnit::new("FALSEUNIT", 99);
+        assert!(c.get_conversion_factor(&pu).is_err());
+        assert!(c.get_dimension(&pu).is_err());
+
+        let pu = ElementUnit::new("W", 1);
+        assert!(matches!(
+            c.get_dimension_name(&pu),
+            Err(UnitError::NoStoredDimension { unit }) if unit == "W"
+        ));
+
+        let mut pu = ElementUnit::new("m", 1);
+        pu.set_dim("FALSEDIMENSION");
+        assert!(matches!(
+            c.get_dimension(&pu),
+            Err(UnitError::NoDimension { dimension }) if dimension == "FALSEDIMENSION"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_embedded_prefix() {
+        let c = MemoryUnitQuery::embedded().await.unwrap();
+        let pu = ElementUnit::new("µs", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 1e-6);
+        assert!(c.get_dimension_name(&pu).unwrap() == *"duration");
+        let pu = ElementUnit::new("MiB", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 8. * 1024. * 1024.);
+        assert!(
+            c.get_conversion_factor(&ElementUnit::new("kmin", 1))
+                .is_err()
+        );
+        let pu = ElementUnit::new("kilometres", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 1e3);
+        let pu = ElementUnit::new("litres", 1);
+        assert!(c.get_dimension_name(&pu).unwrap() == *"volume");
+        assert!(c.get_definition(&pu).unwrap() == Some("dm^3".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_embedded_async() {
+        let c = MemoryUnitQuery::embedded().await.unwrap();
+        let pu = ElementUnit::new("km", 1);
+        assert!(
+            super::AsyncUnitQuery::get_conversion_factor(&c, &pu)
+                .await
+                .unwrap()
+                == 1e3
+        );
+        assert!(
+            super::AsyncUnitQuery::get_dimension_name(&c, &pu)
+                .await
+                .unwrap()
+                == *"length"
+        );
+        let length = Dimension::from([0, 0, 1, 0, 0, 0, 0]);
+        let names = super::AsyncUnitQuery::find_dimension_by_vector(&c, &length).await;
+        assert!(names.unwrap() == ["length"]);
+        let units = super::AsyncUnitQuery::find_units_by_vector(&c, &length).await;
+        assert!(units.unwrap().contains(&"angstrom".to_owned()));
+    }
+}
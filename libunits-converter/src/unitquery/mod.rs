@@ -2,50 +2,542 @@
 
 use crate::{
     UnitError,
-    datatypes::{Dimension, ElementUnit, Unit},
+    datatypes::{Dimension, ElementUnit},
+    definition,
+    prefix::{Prefix, PrefixKind, split_prefix},
 };
 
+mod cache;
+mod memory;
+mod overlay;
 mod sql;
+mod suggest;
+pub use cache::CachedUnitQuery;
+pub use memory::MemoryUnitQuery;
+pub use overlay::{OverlayUnitQuery, UnitRegistry};
 pub use sql::SqlUnitQuery;
 pub trait UnitQuery {
     fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError>;
 
+    /// Offset added after scaling to reach the SI unit (non-zero for °C, °F).
+    fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError>;
+
     fn get_unit_list(&self) -> Result<Vec<String>, UnitError>;
 
     fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError>;
 
     fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError>;
+
+    /// Whether the catalogue unit `unit_name` can be prefixed with the given kind of prefix,
+    /// unknown units accept none.
+    fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError>;
+
+    /// Expression defining `unit` over other catalogue units (`kg*m/s^2` for `N`, `1000*m` for
+    /// `km`), `None` when its row only stores a factor and a dimension.
+    fn get_definition(&self, _unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+        Ok(None)
+    }
+
+    /// Alternative names of catalogue units, as (alias, unit) pairs.
+    fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        Ok(Vec::new())
+    }
+
+    /// Named dimensions of the catalogue, in table order.
+    fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        Ok(Vec::new())
+    }
+
+    /// Names of the quantities of dimension `dimension`, in table order: `energy` and `torque`
+    /// for `[1, -2, 2, 0, 0, 0, 0]`. None for a dimensionless vector, which stands for ratios,
+    /// angles and information alike.
+    fn find_dimension_by_vector(&self, dimension: &Dimension) -> Result<Vec<String>, UnitError> {
+        if dimension.is_dimensionless() {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .get_dimensions()?
+            .into_iter()
+            .filter(|(_, d)| d == dimension)
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Catalogue units of dimension `dimension`, derived ones included, in catalogue order. None
+    /// for a dimensionless vector, as for [`UnitQuery::find_dimension_by_vector`].
+    fn find_units_by_vector(&self, dimension: &Dimension) -> Result<Vec<String>, UnitError> {
+        let mut units = Vec::new();
+        if dimension.is_dimensionless() {
+            return Ok(units);
+        }
+        for name in self.get_unit_list()? {
+            let resolved = definition::resolve(self, &ElementUnit::new(&name, 1));
+            if resolved.is_ok_and(|(_, d)| d == *dimension) {
+                units.push(name);
+            }
+        }
+        Ok(units)
+    }
+
+    /// Catalogue units `unit_name` was likely meant to be, closest first: same name in another
+    /// case, singular, alias or a few typos away (`Kg`, `kgs`, `mni` for `kg`, `kg`, `min`).
+    fn suggest(&self, unit_name: &str) -> Result<Vec<String>, UnitError> {
+        let known: Vec<String> = suggest::variants(unit_name)
+            .into_iter()
+            .filter(|variant| self.resolve_prefix(variant).is_ok())
+            .collect();
+        let (units, aliases) = (self.get_unit_list()?, self.get_aliases()?);
+        Ok(suggest::rank(unit_name, &units, &aliases, &known))
+    }
+
+    /// Decompose a unit missing from the catalogue into prefix + catalogue unit (`nm` → nano, `m`).
+    /// Callers look a name up as is first so that `min` stays a minute and is never read as milli-`in`.
+    fn resolve_prefix(&self, unit_name: &str) -> Result<(&'static Prefix, String), UnitError> {
+        let mut candidates = Vec::new();
+        for (prefix, base) in split_prefix(unit_name) {
+            if self.accepts_prefix(base, prefix.kind)? {
+                candidates.push((prefix, base));
+            }
+        }
+        pick_prefix(unit_name, &candidates)
+    }
+
+    /// Token changing whenever the catalogue does, [`CachedUnitQuery`] drops its content when it
+    /// moves. Must stay cheap, the default is for catalogues that never change.
+    fn revision(&self) -> u64 {
+        0
+    }
+}
+
+/// Non-blocking counterpart of [`UnitQuery`], to be used from an async runtime.
+///
+/// Methods share their name with [`UnitQuery`], import only the trait matching the context.
+pub trait AsyncUnitQuery {
+    fn get_conversion_factor(
+        &self,
+        unit: &ElementUnit,
+    ) -> impl Future<Output = Result<f64, UnitError>>;
+
+    fn get_conversion_offset(
+        &self,
+        unit: &ElementUnit,
+    ) -> impl Future<Output = Result<f64, UnitError>>;
+
+    fn get_unit_list(&self) -> impl Future<Output = Result<Vec<String>, UnitError>>;
+
+    fn get_dimension(
+        &self,
+        unit: &ElementUnit,
+    ) -> impl Future<Output = Result<(String, Dimension), UnitError>>;
+
+    fn get_dimension_name(
+        &self,
+        p_unit: &ElementUnit,
+    ) -> impl Future<Output = Result<String, UnitError>>;
+
+    fn accepts_prefix(
+        &self,
+        unit_name: &str,
+        kind: PrefixKind,
+    ) -> impl Future<Output = Result<bool, UnitError>>;
+
+    /// See [`UnitQuery::get_definition`].
+    fn get_definition(
+        &self,
+        _unit: &ElementUnit,
+    ) -> impl Future<Output = Result<Option<String>, UnitError>> {
+        async { Ok(None) }
+    }
+
+    /// See [`UnitQuery::get_aliases`].
+    fn get_aliases(&self) -> impl Future<Output = Result<Vec<(String, String)>, UnitError>> {
+        async { Ok(Vec::new()) }
+    }
+
+    /// See [`UnitQuery::get_dimensions`].
+    fn get_dimensions(&self) -> impl Future<Output = Result<Vec<(String, Dimension)>, UnitError>> {
+        async { Ok(Vec::new()) }
+    }
+
+    /// See [`UnitQuery::find_dimension_by_vector`].
+    fn find_dimension_by_vector(
+        &self,
+        dimension: &Dimension,
+    ) -> impl Future<Output = Result<Vec<String>, UnitError>> {
+        async move {
+            if dimension.is_dimensionless() {
+                return Ok(Vec::new());
+            }
+            Ok(self
+                .get_dimensions()
+                .await?
+                .into_iter()
+                .filter(|(_, d)| d == dimension)
+                .map(|(name, _)| name)
+                .collect())
+        }
+    }
+
+    /// See [`UnitQuery::find_units_by_vector`].
+    fn find_units_by_vector(
+        &self,
+        dimension: &Dimension,
+    ) -> impl Future<Output = Result<Vec<String>, UnitError>> {
+        async move {
+            let mut units = Vec::new();
+            if dimension.is_dimensionless() {
+                return Ok(units);
+            }
+            for name in self.get_unit_list().await? {
+                let resolved = definition::resolve_async(self, &ElementUnit::new(&name, 1)).await;
+                if resolved.is_ok_and(|(_, d)| d == *dimension) {
+                    units.push(name);
+                }
+            }
+            Ok(units)
+        }
+    }
+
+    /// See [`UnitQuery::suggest`].
+    fn suggest(&self, unit_name: &str) -> impl Future<Output = Result<Vec<String>, UnitError>> {
+        async move {
+            let mut known = Vec::new();
+            for variant in suggest::variants(unit_name) {
+                if self.resolve_prefix(&variant).await.is_ok() {
+                    known.push(variant);
+                }
+            }
+            let (units, aliases) = (self.get_unit_list().await?, self.get_aliases().await?);
+            Ok(suggest::rank(unit_name, &units, &aliases, &known))
+        }
+    }
+
+    /// See [`UnitQuery::resolve_prefix`].
+    fn resolve_prefix(
+        &self,
+        unit_name: &str,
+    ) -> impl Future<Output = Result<(&'static Prefix, String), UnitError>> {
+        async move {
+            let mut candidates = Vec::new();
+            for (prefix, base) in split_prefix(unit_name) {
+                if self.accepts_prefix(base, prefix.kind).await? {
+                    candidates.push((prefix, base));
+                }
+            }
+            pick_prefix(unit_name, &candidates)
+        }
+    }
+
+    /// See [`UnitQuery::revision`].
+    fn revision(&self) -> u64 {
+        0
+    }
+}
+
+fn pick_prefix(
+    unit_name: &str,
+    candidates: &[(&'static Prefix, &str)],
+) -> Result<(&'static Prefix, String), UnitError> {
+    match candidates {
+        [] => Err(UnitError::bad_unit(unit_name)),
+        [(prefix, base)] => Ok((prefix, base.to_string())),
+        _ => Err(UnitError::AmbiguousUnit {
+            name: unit_name.to_owned(),
+            span: None,
+            candidates: candidates
+                .iter()
+                .map(|(prefix, base)| format!("{}-{}", prefix.name, base))
+                .collect(),
+        }),
+    }
+}
+
+/// Runs `query` on `unit_name`, falling back on its prefix decomposition when the catalogue
+/// has no such unit.
+pub(crate) fn lookup<Q: UnitQuery + ?Sized, V>(
+    unit_query: &Q,
+    unit_name: &str,
+    query: impl Fn(&str) -> Result<Option<V>, UnitError>,
+) -> Result<(Option<&'static Prefix>, V), UnitError> {
+    if let Some(value) = query(unit_name)? {
+        return Ok((None, value));
+    }
+    let (prefix, base) = unit_query.resolve_prefix(unit_name)?;
+    match query(&base)? {
+        Some(value) => Ok((Some(prefix), value)),
+        None => Err(UnitError::bad_unit(unit_name)),
+    }
+}
+
+/// Definition of a prefixed unit: the prefix factor times its catalogue unit.
+pub(crate) fn prefixed_definition(prefix: &Prefix, base: &str) -> String {
+    format!("{}*{}", prefix.factor, base)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "embedded"))]
 mod test {
-    use super::*;
+    use super::{SqlUnitQuery, UnitQuery};
+    use crate::{
+        UnitError,
+        datatypes::{Dimension, ElementUnit},
+        migrations::LATEST,
+    };
 
     #[tokio::test]
     async fn test_dimension_name() {
-        let c = SqlUnitQuery::new().await.unwrap();
-        let pu = ElementUnit::new("kg", 1.);
+        let c = SqlUnitQuery::temporary("dimension_name").await;
+        let pu = ElementUnit::new("kg", 1);
         let name = c.get_dimension_name(&pu).unwrap();
         assert!(name == *"mass");
-        let pu = ElementUnit::new("s", 1.);
+        let pu = ElementUnit::new("s", 1);
         let name = c.get_dimension_name(&pu).unwrap();
         assert!(name == *"duration");
 
-        let pu = ElementUnit::new("FALSEUNIT", 99.);
+        let pu = ElementUnit::new("FALSEUNIT", 99);
         assert!(c.get_dimension_name(&pu).is_err());
+
+        // Known only by its definition
+        let pu = ElementUnit::new("W", 1);
+        assert!(matches!(
+            c.get_dimension_name(&pu),
+            Err(UnitError::NoStoredDimension { unit }) if unit == "W"
+        ));
     }
 
     #[tokio::test]
     async fn test_conversion_factor() {
-        let c = SqlUnitQuery::new().await.unwrap();
-        let pu = ElementUnit::new("kg", 1.);
+        let c = SqlUnitQuery::temporary("conversion_factor").await;
+        let pu = ElementUnit::new("kg", 1);
         let cv = c.get_conversion_factor(&pu).unwrap();
         assert!(cv == 1.);
-        let pu = ElementUnit::new("g", 1.);
+        let pu = ElementUnit::new("g", 1);
         let cv = c.get_conversion_factor(&pu).unwrap();
         assert!(cv == 1e-3);
 
-        let pu = ElementUnit::new("FALSEUNIT", 99.);
+        let pu = ElementUnit::new("FALSEUNIT", 99);
+        assert!(c.get_conversion_factor(&pu).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prefixed_unit() {
+        let c = SqlUnitQuery::temporary("prefixed_unit").await;
+        let pu = ElementUnit::new("nm", 1);
+        assert!(c.get_dimension_name(&pu).unwrap() == *"length");
+        assert!(c.get_conversion_factor(&pu).unwrap() == 1e-9);
+
+        let pu = ElementUnit::new("hPa", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 100.);
+        assert!(c.get_dimension(&pu).unwrap().0 == *"pressure");
+
+        let pu = ElementUnit::new("KiB", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 8. * 1024.);
+        let pu = ElementUnit::new("kB", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 8. * 1000.);
+
+        // Catalogue rows win over decomposition, non-SI units take no prefix
+        let pu = ElementUnit::new("min", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 60.);
+        assert!(
+            c.get_conversion_factor(&ElementUnit::new("kmin", 1))
+                .is_err()
+        );
+        assert!(
+            c.get_conversion_factor(&ElementUnit::new("Kim", 1))
+                .is_err()
+        );
+        assert!(
+            c.get_conversion_factor(&ElementUnit::new("kdegC", 1))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alias() {
+        let c = SqlUnitQuery::temporary("alias").await;
+        for name in [
+            "meter",
+            "metres",
+            "kilometre",
+            "pascal",
+            "hPa",
+            "lb",
+            "µm",
+            "um",
+            "mV",
+        ] {
+            let pu = ElementUnit::new(name, 1);
+            assert!(c.get_conversion_factor(&pu).is_ok(), "{}", name);
+        }
+        let pu = ElementUnit::new("kilometers", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 1e3);
+        assert!(c.get_dimension_name(&pu).unwrap() == *"length");
+        assert!(c.get_definition(&pu).unwrap() == Some("1000*m".to_owned()));
+        let pu = ElementUnit::new("joules", 1);
+        assert!(c.get_definition(&pu).unwrap() == Some("N*m".to_owned()));
+        let pu = ElementUnit::new("pounds", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 0.4536);
+
+        // Aliases are not units of their own
+        assert!(!c.get_unit_list().unwrap().contains(&"meter".to_owned()));
+        assert!(
+            c.get_aliases()
+                .unwrap()
+                .contains(&("metre".to_owned(), "m".to_owned()))
+        );
+        assert!(c.suggest("meterss").unwrap() == ["m"]);
+    }
+
+    #[tokio::test]
+    async fn test_dimension_by_vector() {
+        let c = SqlUnitQuery::temporary("dimension_by_vector").await;
+        let energy = Dimension::from([1, -2, 2, 0, 0, 0, 0]);
+        assert!(c.find_dimension_by_vector(&energy).unwrap() == ["energy", "torque"]);
+        let units = c.find_units_by_vector(&energy).unwrap();
+        assert!(units.starts_with(&["J".to_owned(), "cal".to_owned(), "kcal".to_owned()]));
+        assert!(units.contains(&"kWh".to_owned()));
+        let power = Dimension::from([1, -3, 2, 0, 0, 0, 0]);
+        assert!(c.find_units_by_vector(&power).unwrap() == ["W"]);
+
+        let nothing = Dimension::from([0, 0, 0, 0, 0, 0, 1]);
+        assert!(c.find_dimension_by_vector(&nothing).unwrap().is_empty());
+        assert!(c.find_units_by_vector(&nothing).unwrap().is_empty());
+        // `information` is stored dimensionless, as any ratio
+        let ratio = Dimension::default();
+        assert!(c.find_dimension_by_vector(&ratio).unwrap().is_empty());
+        assert!(c.find_units_by_vector(&ratio).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_suggest() {
+        let c = SqlUnitQuery::temporary("suggest").await;
+        assert!(c.suggest("Kg").unwrap() == ["kg"]);
+        assert!(c.suggest("kgs").unwrap() == ["kg"]);
+        assert!(c.suggest("bars").unwrap() == ["bar"]);
+        assert!(c.suggest("Km").unwrap() == ["km"]);
+        assert!(c.suggest("mni").unwrap().contains(&"min".to_owned()));
+        assert!(c.suggest("zz").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_open() {
+        let missing = std::path::Path::new("/nonexistent/runit/sqlite.db");
+        assert!(SqlUnitQuery::locate(Some(missing)).unwrap() == missing);
+        assert!(matches!(
+            SqlUnitQuery::open(missing).await,
+            Err(UnitError::MissingDatabase(_))
+        ));
+        assert!(!missing.exists());
+
+        let path = SqlUnitQuery::temporary("open").await.path().to_owned();
+        let c = SqlUnitQuery::open(&path).await.unwrap();
+        assert!(c.get_unit_list().unwrap().contains(&"kg".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_open_outdated() {
+        let path = std::env::temp_dir().join(format!("runit-outdated-{}.db", std::process::id()));
+        for stale in [path.clone(), path.with_extension("db-wal")] {
+            let _ = std::fs::remove_file(stale);
+        }
+        // Migrated by hand, the queries of the catalogue may need the last migration
+        let db = turso::Builder::new_local(&path.to_string_lossy())
+            .build()
+            .await
+            .unwrap();
+        let migrations = crate::migrations::embedded();
+        let previous = &migrations[..migrations.len() - 1];
+        crate::migrations::migrate(&db.connect().unwrap(), previous)
+            .await
+            .unwrap();
+        drop(db);
+        assert!(matches!(
+            SqlUnitQuery::open(&path).await,
+            Err(UnitError::BadDatabase(_, reason))
+                if reason == format!("at version {}, run init_db to migrate", LATEST - 1)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_quoted_unit_name() {
+        let c = SqlUnitQuery::temporary("quoted_unit_name").await;
+        let pu = ElementUnit::new("ft'", 1);
+        assert!(c.get_conversion_factor(&pu).unwrap() == 0.3048);
+        let pu = ElementUnit::new("in\"", 1);
+        assert!(c.get_dimension_name(&pu).unwrap() == *"length");
+
+        // Names are bound as parameters, never spliced in the query
+        let pu = ElementUnit::new("x' OR unit_name = 'kg", 1);
         assert!(c.get_conversion_factor(&pu).is_err());
+        assert!(c.get_dimension_name(&pu).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_conversion_offset() {
+        let c = SqlUnitQuery::temporary("conversion_offset").await;
+        let pu = ElementUnit::new("K", 1);
+        assert!(c.get_conversion_offset(&pu).unwrap() == 0.);
+        let pu = ElementUnit::new("degC", 1);
+        assert!(c.get_conversion_offset(&pu).unwrap() == 273.15);
+        let pu = ElementUnit::new("kg", 1);
+        assert!(c.get_conversion_offset(&pu).unwrap() == 0.);
+
+        let pu = ElementUnit::new("FALSEUNIT", 99);
+        assert!(c.get_conversion_offset(&pu).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_async_query() {
+        let c = SqlUnitQuery::temporary("async_query").await;
+        let pu = ElementUnit::new("hPa", 1);
+        assert!(
+            super::AsyncUnitQuery::get_conversion_factor(&c, &pu)
+                .await
+                .unwrap()
+                == 100.
+        );
+        assert!(
+            super::AsyncUnitQuery::get_dimension(&c, &pu)
+                .await
+                .unwrap()
+                .0
+                == *"pressure"
+        );
+        let pu = ElementUnit::new("degC", 1);
+        assert!(
+            super::AsyncUnitQuery::get_conversion_offset(&c, &pu)
+                .await
+                .unwrap()
+                == 273.15
+        );
+
+        let pu = ElementUnit::new("FALSEUNIT", 99);
+        assert!(
+            super::AsyncUnitQuery::get_dimension_name(&c, &pu)
+                .await
+                .is_err()
+        );
+        assert!(
+            super::AsyncUnitQuery::get_unit_list(&c)
+                .await
+                .unwrap()
+                .contains(&"kg".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shared_between_threads() {
+        let c = SqlUnitQuery::temporary("shared_between_threads").await;
+        std::thread::scope(|s| {
+            for (name, factor) in [("km", 1e3), ("g", 1e-3), ("h", 3600.), ("KiB", 8192.)] {
+                let c = &c;
+                s.spawn(move || {
+                    for _ in 0..20 {
+                        let pu = ElementUnit::new(name, 1);
+                        assert!(c.get_conversion_factor(&pu).unwrap() == factor);
+                        assert!(c.get_unit_list().unwrap().len() > 30);
+                    }
+                });
+            }
+        });
     }
 }
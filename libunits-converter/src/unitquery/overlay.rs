@@ -0,0 +1,626 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use futures::executor::block_on;
+
+use crate::{
+    Diagnostic, Rational, UnitError,
+    check::check_catalogue,
+    datatypes::{Dimension, ElementUnit},
+    definition,
+    migrations::Migration,
+    parser::{InlineUnitParser, UnitParser},
+    prefix::{Prefix, PrefixKind},
+};
+
+use super::sql::data_home;
+use super::{AsyncUnitQuery, SqlUnitQuery, UnitQuery};
+
+/// Migrations of the user database, its tables have the same columns as the shipped catalogue.
+const USER_MIGRATIONS: [(&str, &str); 2] = [
+    ("001_user_units.sql", USER_SCHEMA),
+    ("002_user_aliases.sql", USER_ALIASES),
+];
+
+const USER_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS dimension (
+  iddimension integer PRIMARY KEY
+, mass double DEFAULT '0'
+, duration double DEFAULT '0'
+, length double DEFAULT '0'
+, temperature double DEFAULT '0'
+, current double DEFAULT '0'
+, amount double DEFAULT '0'
+, luminosity double DEFAULT '0'
+, dimension_name varchar(45) NOT NULL
+);
+CREATE TABLE IF NOT EXISTS conversiontable (
+  id integer PRIMARY KEY
+, unit_name varchar(45) NOT NULL
+, dimension_name varchar(45) NOT NULL
+, conversionfactor double NOT NULL
+, conversionoffset double DEFAULT 0.0
+, si_prefix integer DEFAULT 0
+, binary_prefix integer DEFAULT 0
+, definition text DEFAULT NULL
+);
+";
+
+/// Aliases of the user units, same table as the shipped one.
+const USER_ALIASES: &str = "
+CREATE TABLE IF NOT EXISTS alias (
+  id integer PRIMARY KEY
+, alias_name varchar(45) NOT NULL
+, unit_name varchar(45) NOT NULL
+);
+";
+
+fn user_migrations() -> Vec<Migration> {
+    USER_MIGRATIONS
+        .iter()
+        .filter_map(|(name, sql)| Migration::new(name, *sql))
+        .collect()
+}
+
+/// Catalogue which can be extended at runtime.
+pub trait UnitRegistry {
+    /// Declares `name` as `factor` times the unit expression `expr` (`knot` is 1852 `m/h`). The
+    /// dimension of `expr` must already be known under some name, the expression is kept as the
+    /// unit definition.
+    fn define_unit(
+        &self,
+        name: &str,
+        expr: &str,
+        factor: f64,
+    ) -> impl Future<Output = Result<(), UnitError>>;
+
+    fn define_dimension(
+        &self,
+        name: &str,
+        dimension: Dimension,
+    ) -> impl Future<Output = Result<(), UnitError>>;
+
+    /// Removes a unit defined with [`UnitRegistry::define_unit`] and its aliases, shipped units
+    /// stay.
+    fn remove_unit(&self, name: &str) -> impl Future<Output = Result<(), UnitError>>;
+}
+
+/// Shipped catalogue with the units and dimensions defined by the user on top. These live in a
+/// database of their own so that the shipped one can stay read-only and be replaced on upgrade.
+///
+/// Names are unique across both: a user unit can neither shadow a shipped unit nor one of its
+/// prefixed forms.
+pub struct OverlayUnitQuery {
+    base: SqlUnitQuery,
+    user: Option<SqlUnitQuery>,
+    user_path: PathBuf,
+}
+
+impl OverlayUnitQuery {
+    /// Environment variable overriding the user database location.
+    pub const USER_DB_ENV: &str = "RUNIT_USER_DB";
+    const USER_DB_FILE: &str = "runit-converter/user.db";
+
+    /// Opens the databases found by [`SqlUnitQuery::locate`] and [`OverlayUnitQuery::locate`].
+    pub async fn new(
+        db_path: Option<&Path>,
+        user_db_path: Option<&Path>,
+    ) -> Result<Self, UnitError> {
+        let base = SqlUnitQuery::open(SqlUnitQuery::locate(db_path)?).await?;
+        Self::open(base, Self::locate(user_db_path)?).await
+    }
+
+    /// Layers the user database at `user_path` over `base`, nothing is created when missing.
+    pub async fn open(base: SqlUnitQuery, user_path: impl AsRef<Path>) -> Result<Self, UnitError> {
+        let user_path = user_path.as_ref();
+        let user = if user_path.is_file() {
+            Some(SqlUnitQuery::connect(user_path, Some(&user_migrations())).await?)
+        } else {
+            None
+        };
+        Ok(Self {
+            base,
+            user,
+            user_path: user_path.to_owned(),
+        })
+    }
+
+    /// Same as [`OverlayUnitQuery::open`], creating the user database when missing.
+    pub async fn create(
+        base: SqlUnitQuery,
+        user_path: impl AsRef<Path>,
+    ) -> Result<Self, UnitError> {
+        let user_path = user_path.as_ref();
+        if let Some(dir) = user_path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                UnitError::BadDatabase(user_path.display().to_string(), e.to_string())
+            })?;
+        }
+        let user = SqlUnitQuery::connect(user_path, Some(&user_migrations())).await?;
+        Ok(Self {
+            base,
+            user: Some(user),
+            user_path: user_path.to_owned(),
+        })
+    }
+
+    /// User database location: `explicit` path (`--user-db` flag), `RUNIT_USER_DB`, then
+    /// `runit-converter/user.db` in `$XDG_DATA_HOME` (`~/.local/share`).
+    pub fn locate(explicit: Option<&Path>) -> Result<PathBuf, UnitError> {
+        if let Some(path) = explicit {
+            return Ok(path.to_path_buf());
+        }
+        if let Some(path) = std::env::var_os(Self::USER_DB_ENV).filter(|p| !p.is_empty()) {
+            return Ok(PathBuf::from(path));
+        }
+        data_home()
+            .map(|dir| dir.join(Self::USER_DB_FILE))
+            .ok_or_else(|| UnitError::MissingDatabase(Self::USER_DB_FILE.to_owned()))
+    }
+
+    pub fn user_path(&self) -> &Path {
+        &self.user_path
+    }
+
+    /// [`SqlUnitQuery::check`] over both databases as one catalogue, so that user units
+    /// shadowing shipped ones are reported as duplicates.
+    pub async fn check(&self) -> Result<Vec<Diagnostic>, UnitError> {
+        let (mut units, mut dimensions) = self.base.rows().await?;
+        let mut aliases = self.base.aliases().await?;
+        if let Some(user) = &self.user {
+            let (user_units, user_dimensions) = user.rows().await?;
+            units.extend(user_units);
+            dimensions.extend(user_dimensions);
+            aliases.extend(user.aliases().await?);
+        }
+        Ok(check_catalogue(self, &units, &dimensions, &aliases).await)
+    }
+
+    fn user(&self) -> Result<&SqlUnitQuery, UnitError> {
+        self.user
+            .as_ref()
+            .ok_or_else(|| UnitError::MissingDatabase(self.user_path.display().to_string()))
+    }
+
+    /// Layer answering for `unit_name`: user units, shipped units with or without prefix, then
+    /// prefixed user units.
+    async fn layer(&self, unit_name: &str) -> Result<&SqlUnitQuery, UnitError> {
+        if let Some(user) = &self.user
+            && user.has_unit(unit_name).await?
+        {
+            return Ok(user);
+        }
+        if self.base.has_unit(unit_name).await? {
+            return Ok(&self.base);
+        }
+        match AsyncUnitQuery::resolve_prefix(&self.base, unit_name).await {
+            Ok(_) => return Ok(&self.base),
+            Err(UnitError::BadUnit { .. }) => {}
+            Err(e) => return Err(e),
+        }
+        if let Some(user) = &self.user {
+            AsyncUnitQuery::resolve_prefix(user, unit_name).await?;
+            return Ok(user);
+        }
+        Err(UnitError::bad_unit(unit_name))
+    }
+
+    async fn find_dimension(&self, dim_name: &str) -> Result<Option<Dimension>, UnitError> {
+        if let Some(user) = &self.user
+            && let Some(dimension) = user.find_dimension(dim_name).await?
+        {
+            return Ok(Some(dimension));
+        }
+        self.base.find_dimension(dim_name).await
+    }
+}
+
+impl AsyncUnitQuery for OverlayUnitQuery {
+    async fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        let layer = self.layer(&unit.name).await?;
+        AsyncUnitQuery::get_conversion_factor(layer, unit).await
+    }
+
+    async fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        let layer = self.layer(&unit.name).await?;
+        AsyncUnitQuery::get_conversion_offset(layer, unit).await
+    }
+
+    async fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+        let mut names = AsyncUnitQuery::get_unit_list(&self.base).await?;
+        if let Some(user) = &self.user {
+            names.extend(user.unit_names().await?);
+        }
+        Ok(names)
+    }
+
+    async fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+        let dim_name = match &unit.dim {
+            Some(name) => name.to_owned(),
+            None => AsyncUnitQuery::get_dimension_name(self, unit).await?,
+        };
+        match self.find_dimension(&dim_name).await? {
+            Some(dimension) => Ok((dim_name, dimension)),
+            None => Err(UnitError::NoDimension {
+                dimension: dim_name,
+            }),
+        }
+    }
+
+    async fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
+        let layer = self.layer(&p_unit.name).await?;
+        AsyncUnitQuery::get_dimension_name(layer, p_unit).await
+    }
+
+    async fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError> {
+        if let Some(user) = &self.user
+            && user.has_unit(unit_name).await?
+        {
+            return AsyncUnitQuery::accepts_prefix(user, unit_name, kind).await;
+        }
+        AsyncUnitQuery::accepts_prefix(&self.base, unit_name, kind).await
+    }
+
+    async fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+        let layer = self.layer(&unit.name).await?;
+        AsyncUnitQuery::get_definition(layer, unit).await
+    }
+
+    async fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        let mut aliases = self.base.aliases().await?;
+        if let Some(user) = &self.user {
+            aliases.extend(user.aliases().await?);
+        }
+        Ok(aliases)
+    }
+
+    /// Shipped dimensions first.
+    async fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        let mut dimensions = self.base.dimensions().await?;
+        if let Some(user) = &self.user {
+            dimensions.extend(user.dimensions().await?);
+        }
+        Ok(dimensions)
+    }
+
+    fn revision(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        UnitQuery::revision(&self.base).hash(&mut hasher);
+        self.user
+            .as_ref()
+            .map(UnitQuery::revision)
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl UnitRegistry for OverlayUnitQuery {
+    async fn define_unit(&self, name: &str, expr: &str, factor: f64) -> Result<(), UnitError> {
+        let user = self.user()?;
+        if !(factor.is_finite() && factor > 0.) {
+            return Err(UnitError::Custom(format!(
+                "Factor {} of {} must be positive",
+                factor, name
+            )));
+        }
+        // The name must read back as a single unit
+        let parser = InlineUnitParser::default();
+        match parser.parse_unit(name) {
+            Ok(unit) if unit.partials.len() == 1 && unit.partials[0].name == name => {}
+            _ => return Err(UnitError::bad_unit(name)),
+        }
+        match self.layer(name).await {
+            Ok(_) => {
+                return Err(UnitError::Custom(format!("Unit {} already exists", name)));
+            }
+            Err(UnitError::BadUnit { .. }) => {}
+            Err(e) => return Err(e),
+        }
+
+        let unit = parser.parse_unit(expr)?;
+        let mut si_factor = factor;
+        let mut dimension = Dimension::default();
+        for partial in &unit.partials {
+            let (cf, dim) = definition::resolve_async(self, partial).await?;
+            si_factor *= cf.powf(partial.exp().to_f64());
+            dimension = dimension.dot(&dim, partial.exp())?;
+        }
+        // Dimension of the unit it scales (`4 bit` is an information), else the first name given
+        // to the dimension, shipped names first. Dimensionless rows count here, unlike in
+        // `find_dimension_by_vector`.
+        let mut dim_name = match unit.partials.as_slice() {
+            [partial] if partial.exp() == Rational::ONE => {
+                match AsyncUnitQuery::get_dimension_name(self, partial).await {
+                    Ok(name) => Some(name),
+                    Err(UnitError::NoStoredDimension { .. }) => None,
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => None,
+        };
+        if dim_name.is_none() {
+            dim_name = AsyncUnitQuery::get_dimensions(self)
+                .await?
+                .into_iter()
+                .find(|(_, d)| *d == dimension)
+                .map(|(name, _)| name);
+        }
+        let dim_name = dim_name.ok_or_else(|| {
+            UnitError::Custom(format!(
+                "No dimension {} is defined for {}, add it first",
+                dimension, expr
+            ))
+        })?;
+
+        let definition = if factor == 1. {
+            expr.to_owned()
+        } else {
+            format!("{}*{}", factor, expr)
+        };
+        user.execute(
+            "INSERT INTO conversiontable (unit_name, dimension_name, conversionfactor, definition)
+            VALUES (?1, ?2, ?3, ?4)",
+            vec![
+                turso::Value::Text(name.to_owned()),
+                turso::Value::Text(dim_name),
+                turso::Value::Real(si_factor),
+                turso::Value::Text(definition),
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn define_dimension(&self, name: &str, dimension: Dimension) -> Result<(), UnitError> {
+        let user = self.user()?;
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(UnitError::Custom(format!("Bad dimension name {:?}", name)));
+        }
+        if self.find_dimension(name).await?.is_some() {
+            return Err(UnitError::Custom(format!(
+                "Dimension {} already exists",
+                name
+            )));
+        }
+
+        let mut values: Vec<turso::Value> = dimension
+            .0
+            .iter()
+            .map(|exp| turso::Value::Real(exp.to_f64()))
+            .collect();
+        values.push(turso::Value::Text(name.to_owned()));
+        user.execute(
+            "INSERT INTO dimension
+            (mass, duration, length, temperature, current, amount, luminosity, dimension_name)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            values,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn remove_unit(&self, name: &str) -> Result<(), UnitError> {
+        if let Some(user) = &self.user
+            && user.has_unit(name).await?
+        {
+            user.execute("DELETE FROM conversiontable WHERE unit_name = ?1", [name])
+                .await?;
+            user.execute(
+                "DELETE FROM alias WHERE unit_name = ?1 OR alias_name = ?1",
+                [name],
+            )
+            .await?;
+            return Ok(());
+        }
+        if self.base.has_unit(name).await? {
+            return Err(UnitError::Custom(format!(
+                "{} is a shipped unit and cannot be removed",
+                name
+            )));
+        }
+        Err(UnitError::bad_unit(name))
+    }
+}
+
+/// Blocking facade over [`AsyncUnitQuery`], must not be called from inside an async runtime.
+impl UnitQuery for OverlayUnitQuery {
+    fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        block_on(AsyncUnitQuery::get_conversion_factor(self, unit))
+    }
+
+    fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        block_on(AsyncUnitQuery::get_conversion_offset(self, unit))
+    }
+
+    fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+        block_on(AsyncUnitQuery::get_unit_list(self))
+    }
+
+    fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+        block_on(AsyncUnitQuery::get_dimension(self, unit))
+    }
+
+    fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
+        block_on(AsyncUnitQuery::get_dimension_name(self, p_unit))
+    }
+
+    fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError> {
+        block_on(AsyncUnitQuery::accepts_prefix(self, unit_name, kind))
+    }
+
+    fn resolve_prefix(&self, unit_name: &str) -> Result<(&'static Prefix, String), UnitError> {
+        block_on(AsyncUnitQuery::resolve_prefix(self, unit_name))
+    }
+
+    fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+        block_on(AsyncUnitQuery::get_definition(self, unit))
+    }
+
+    fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        block_on(AsyncUnitQuery::get_aliases(self))
+    }
+
+    fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        block_on(AsyncUnitQuery::get_dimensions(self))
+    }
+
+    fn revision(&self) -> u64 {
+        AsyncUnitQuery::revision(self)
+    }
+}
+
+#[cfg(all(test, feature = "embedded"))]
+mod test {
+    use super::{AsyncUnitQuery, OverlayUnitQuery, SqlUnitQuery, UnitRegistry};
+    use crate::{DefinitionIssue, Diagnostic, Dimension, ElementUnit, Rational, UnitError};
+
+    async fn overlay(test: &str) -> OverlayUnitQuery {
+        let path = std::env::temp_dir().join(format!("runit-{}-{}.db", test, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let base = SqlUnitQuery::temporary(test).await;
+        OverlayUnitQuery::create(base, &path).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_define_unit() {
+        let c = overlay("define-unit").await;
+        let speed = Dimension::from([0, -1, 1, 0, 0, 0, 0]);
+        assert!(c.define_unit("knot", "m/h", 1852.).await.is_err());
+        c.define_dimension("speed", speed).await.unwrap();
+        c.define_unit("knot", "m/h", 1852.).await.unwrap();
+        let pu = ElementUnit::new("knot", 1);
+        let factor = AsyncUnitQuery::get_conversion_factor(&c, &pu)
+            .await
+            .unwrap();
+        assert!((factor - 1852. / 3600.).abs() < 1e-12);
+        let (name, dim) = AsyncUnitQuery::get_dimension(&c, &pu).await.unwrap();
+        assert!(name == *"speed");
+        assert!(dim == speed);
+
+        // Shipped units are still there, names are unique across both databases
+        let pu = ElementUnit::new("km", 1);
+        assert!(
+            AsyncUnitQuery::get_conversion_factor(&c, &pu)
+                .await
+                .unwrap()
+                == 1e3
+        );
+        assert!(c.define_unit("knot", "m/s", 1.).await.is_err());
+        assert!(c.define_unit("kg", "g", 1000.).await.is_err());
+        assert!(c.define_unit("Mm", "m", 1e6).await.is_err());
+        assert!(c.define_unit("k not", "m", 1.).await.is_err());
+        assert!(c.define_unit("zero", "m", 0.).await.is_err());
+        assert!(c.define_unit("bad", "FALSEUNIT", 1.).await.is_err());
+
+        let names = AsyncUnitQuery::get_unit_list(&c).await.unwrap();
+        assert!(names.contains(&"knot".to_owned()) && names.contains(&"kg".to_owned()));
+
+        // Named after the unit scaled, dimensionless ones included
+        c.define_unit("nibble", "bit", 4.).await.unwrap();
+        let pu = ElementUnit::new("nibble", 1);
+        let name = AsyncUnitQuery::get_dimension_name(&c, &pu).await.unwrap();
+        assert!(name == *"information");
+        c.define_unit("hp", "W", 745.7).await.unwrap();
+        let pu = ElementUnit::new("hp", 1);
+        let name = AsyncUnitQuery::get_dimension_name(&c, &pu).await.unwrap();
+        assert!(name == *"power");
+
+        c.remove_unit("knot").await.unwrap();
+        let pu = ElementUnit::new("knot", 1);
+        assert!(
+            AsyncUnitQuery::get_conversion_factor(&c, &pu)
+                .await
+                .is_err()
+        );
+        assert!(matches!(
+            c.remove_unit("kg").await,
+            Err(UnitError::Custom(_))
+        ));
+        assert!(matches!(
+            c.remove_unit("knot").await,
+            Err(UnitError::BadUnit { .. })
+        ));
+        std::fs::remove_file(c.user_path()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_define_dimension() {
+        let c = overlay("define-dimension").await;
+        // Spectral noise density V/sqrt(Hz)
+        let mut noise = Dimension::from([1, -3, 2, 0, -1, 0, 0]);
+        noise.0[1] = Rational::new(-5, 2);
+        assert!(c.define_unit("nvhz", "nv*s^(1/2)", 1.).await.is_err());
+        c.define_dimension("noise_density", noise).await.unwrap();
+        assert!(c.define_dimension("noise_density", noise).await.is_err());
+        assert!(c.define_dimension("mass", noise).await.is_err());
+
+        c.define_unit("nvhz", "nv*s^(1/2)", 1.).await.unwrap();
+        let pu = ElementUnit::new("nvhz", 1);
+        let (name, dim) = AsyncUnitQuery::get_dimension(&c, &pu).await.unwrap();
+        assert!(name == *"noise_density");
+        assert!(dim == noise);
+        std::fs::remove_file(c.user_path()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let c = overlay("check").await;
+        assert!(c.base.check().await.unwrap().is_empty());
+        c.define_dimension("speed", Dimension::from([0, -1, 1, 0, 0, 0, 0]))
+            .await
+            .unwrap();
+        c.define_unit("knot", "m/h", 1852.).await.unwrap();
+        assert!(c.check().await.unwrap().is_empty());
+
+        // Rows written behind the registry back
+        let user = c.user().unwrap();
+        for sql in [
+            "INSERT INTO conversiontable (unit_name, dimension_name, conversionfactor)
+            VALUES ('kg', 'mass', 1.0)",
+            "INSERT INTO conversiontable (unit_name, dimension_name, conversionfactor)
+            VALUES ('furlong', 'lenght', 201.168)",
+            "UPDATE conversiontable SET conversionfactor = 1.0 WHERE unit_name = 'knot'",
+        ] {
+            user.execute(sql, ()).await.unwrap();
+        }
+        let diagnostics = c.check().await.unwrap();
+        assert!(diagnostics.len() == 3, "{:?}", diagnostics);
+        assert!(diagnostics.contains(&Diagnostic::DuplicateUnit {
+            unit: "kg".to_owned(),
+            count: 2
+        }));
+        assert!(diagnostics.contains(&Diagnostic::UnknownDimension {
+            unit: "furlong".to_owned(),
+            dimension: "lenght".to_owned()
+        }));
+        assert!(diagnostics.iter().any(|d| matches!(
+            d,
+            Diagnostic::Definition(DefinitionIssue::Factor { unit, .. }) if unit == "knot"
+        )));
+        std::fs::remove_file(c.user_path()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_user_db() {
+        let path =
+            std::env::temp_dir().join(format!("runit-missing-user-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let c = OverlayUnitQuery::open(SqlUnitQuery::temporary("missing_user_db").await, &path)
+            .await
+            .unwrap();
+        assert!(!path.exists());
+        let pu = ElementUnit::new("kg", 1);
+        assert!(
+            AsyncUnitQuery::get_conversion_factor(&c, &pu)
+                .await
+                .unwrap()
+                == 1.
+        );
+        assert!(matches!(
+            c.define_unit("knot", "m/h", 1852.).await,
+            Err(UnitError::MissingDatabase(_))
+        ));
+    }
+}
@@ -1,173 +1,658 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::{
-    UnitError,
-    datatypes::{Dimension, ElementUnit},
+    Diagnostic, UnitError,
+    check::{DimensionRow, UnitRow, check_catalogue},
+    datatypes::{Dimension, ElementUnit, Rational},
+    migrations::{LATEST, Migration, current_version, migrate},
+    prefix::{Prefix, PrefixKind},
 };
 use futures::executor::block_on;
+use futures::lock::Mutex;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
 use turso;
 
-use super::UnitQuery;
+use super::{AsyncUnitQuery, UnitQuery, prefixed_definition};
 
 pub struct SqlUnitQuery {
+    path: PathBuf,
     _db: turso::Database,
     conn: turso::Connection,
+    statements: Statements,
+    /// Prepared statements are shared, a query and the reading of its rows must not interleave
+    /// with another one.
+    lock: Mutex<()>,
+}
+
+/// Statements prepared once when the database is opened, every value is bound as a parameter.
+struct Statements {
+    unit_list: turso::Statement,
+    dimension_name: turso::Statement,
+    dimension: turso::Statement,
+    dimensions: turso::Statement,
+    conversion_factor: turso::Statement,
+    conversion_offset: turso::Statement,
+    si_prefix: turso::Statement,
+    binary_prefix: turso::Statement,
+    definition: turso::Statement,
+    alias: turso::Statement,
+    aliases: turso::Statement,
+}
+
+impl Statements {
+    async fn prepare(conn: &turso::Connection) -> turso::Result<Self> {
+        let by_unit = |column: &str| {
+            format!(
+                "SELECT {} FROM {} WHERE unit_name = ?1",
+                column,
+                SqlUnitQuery::TABLE_NAME
+            )
+        };
+        Ok(Self {
+            unit_list: conn
+                .prepare(&format!(
+                    "SELECT unit_name FROM {}",
+                    SqlUnitQuery::TABLE_NAME
+                ))
+                .await?,
+            dimension_name: conn.prepare(&by_unit("dimension_name")).await?,
+            dimension: conn
+                .prepare(&format!(
+                    "SELECT {} FROM dimension WHERE dimension_name = ?1",
+                    DIMENSION_COLUMNS
+                ))
+                .await?,
+            dimensions: conn
+                .prepare(&format!(
+                    "SELECT {} FROM dimension ORDER BY iddimension",
+                    DIMENSION_COLUMNS
+                ))
+                .await?,
+            conversion_factor: conn.prepare(&by_unit("conversionfactor")).await?,
+            conversion_offset: conn.prepare(&by_unit("conversionoffset")).await?,
+            si_prefix: conn.prepare(&by_unit("si_prefix")).await?,
+            binary_prefix: conn.prepare(&by_unit("binary_prefix")).await?,
+            definition: conn.prepare(&by_unit("definition")).await?,
+            alias: conn
+                .prepare("SELECT unit_name FROM alias WHERE alias_name = ?1")
+                .await?,
+            aliases: conn
+                .prepare("SELECT alias_name, unit_name FROM alias ORDER BY id")
+                .await?,
+        })
+    }
+}
+
+pub(super) const DIMENSION_COLUMNS: &str =
+    "dimension_name, mass, duration, length, temperature, current, amount, luminosity";
+
+pub(super) fn sql_error(e: turso::Error) -> UnitError {
+    UnitError::Query(format!("SqlQuery: {}", e))
+}
+
+/// Numeric cell, `NULL` reads as 0 like the column defaults.
+pub(super) fn as_number(value: turso::Value) -> Option<f64> {
+    match value {
+        turso::Value::Real(v) => Some(v),
+        turso::Value::Integer(v) => Some(v as f64),
+        turso::Value::Null => Some(0.),
+        _ => None,
+    }
+}
+
+/// `$XDG_DATA_HOME`, `~/.local/share` when unset.
+pub(super) fn data_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+}
+
+/// Reads a row selected with [`DIMENSION_COLUMNS`].
+pub(super) fn dimension_from_row(row: &turso::Row) -> Result<(String, Dimension), UnitError> {
+    assert!(row.column_count() == 8);
+    let dimension_name = row.get_value(0).unwrap().as_text().unwrap().to_owned();
+    let mut dimension = Dimension::default();
+    for i in 0..dimension.0.len() {
+        let exp = as_number(row.get_value(i + 1).map_err(sql_error)?).unwrap_or(f64::NAN);
+        dimension.0[i] = Rational::from_f64(exp).ok_or_else(|| {
+            UnitError::Query(format!(
+                "SqlQuery: {} exponent {} of {} is not a fraction",
+                Dimension::NAMES[i],
+                exp,
+                dimension_name
+            ))
+        })?;
+    }
+    Ok((dimension_name, dimension))
 }
 
 impl SqlUnitQuery {
     const TABLE_NAME: &str = "conversiontable";
+    /// Environment variable overriding the database location.
+    pub const DB_ENV: &str = "RUNIT_DB";
+    const DB_FILE: &str = "runit-converter/sqlite.db";
 
-    pub async fn new() -> turso::Result<Self> {
-        let _db = turso::Builder::new_local(env!("DB_PATH")).build().await?;
-        let conn = _db.connect()?;
-        let queryself = Self { conn, _db };
-        assert!(queryself.check_db_integrity().await);
-        Ok(queryself)
+    /// Opens the database found by [`SqlUnitQuery::locate`].
+    pub async fn new() -> Result<Self, UnitError> {
+        Self::open(Self::locate(None)?).await
     }
 
-    async fn impl_query_unit_list(&self) -> Result<Vec<String>, UnitError> {
-        let query = format!(
-            "SELECT unit_name, dimension_name FROM {};",
-            Self::TABLE_NAME
-        );
-        let mut rows = self.conn.query(&query, ()).await.unwrap();
-        let mut names = Vec::new();
-        while let Some(row) = rows.next().await.unwrap() {
-            let name = row.get_value(0).unwrap();
-            names.push(name.as_text().unwrap().to_owned());
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, UnitError> {
+        let path = path.as_ref();
+        // Turso creates missing files, an empty catalogue is never what the caller wants
+        if !path.is_file() {
+            return Err(UnitError::MissingDatabase(path.display().to_string()));
         }
+        Self::connect(path, None).await
+    }
 
-        if names.is_empty() {
-            Err(UnitError::Custom("Empty databse".to_owned()))
-        } else {
-            Ok(names)
+    /// Opens `path`, bringing it up to date with `migrations` when given instead of checking the
+    /// catalogue is populated (the user overlay starts empty).
+    pub(super) async fn connect(
+        path: &Path,
+        migrations: Option<&[Migration]>,
+    ) -> Result<Self, UnitError> {
+        let open_error =
+            |e: turso::Error| UnitError::BadDatabase(path.display().to_string(), e.to_string());
+        let _db = turso::Builder::new_local(&path.to_string_lossy())
+            .build()
+            .await
+            .map_err(open_error)?;
+        let conn = _db.connect().map_err(open_error)?;
+        match migrations {
+            Some(migrations) => {
+                migrate(&conn, migrations).await.map_err(|e| {
+                    UnitError::BadDatabase(path.display().to_string(), e.to_string())
+                })?;
+            }
+            None => {
+                let bad_database =
+                    |reason| UnitError::BadDatabase(path.display().to_string(), reason);
+                Self::check_db_integrity(&conn)
+                    .await
+                    .map_err(bad_database)?;
+                let version = current_version(&conn).await?;
+                if version < LATEST {
+                    return Err(bad_database(format!(
+                        "at version {}, run init_db to migrate",
+                        version
+                    )));
+                }
+            }
         }
+        let statements = Statements::prepare(&conn).await.map_err(open_error)?;
+        Ok(Self {
+            path: path.to_owned(),
+            conn,
+            _db,
+            statements,
+            lock: Mutex::new(()),
+        })
     }
 
-    fn get_query_dimension(unit_name: &str) -> String {
-        format!(
-            "SELECT dimension_name FROM conversiontable WHERE unit_name = '{}'",
-            unit_name
-        )
+    /// File the catalogue was opened from.
+    pub fn path(&self) -> &Path {
+        &self.path
     }
 
-    async fn impl_get_dim_name(&self, unit_name: &str) -> Result<String, UnitError> {
-        let query = Self::get_query_dimension(unit_name);
-        let mut rows = self.conn.query(&query, ()).await.unwrap();
-        let row = rows.next().await.unwrap();
-        if row.is_none() {
-            return Err(UnitError::Query(
-                "SqlQuery: should have exactly one result".to_owned(),
-            ));
+    /// Database location, first match wins:
+    /// 1. `explicit` path (`--db` flag),
+    /// 2. `RUNIT_DB` environment variable,
+    /// 3. `runit-converter/sqlite.db` in `$XDG_DATA_HOME` (`~/.local/share`) then `$XDG_DATA_DIRS`,
+    /// 4. the `DB_PATH` given at build time.
+    ///
+    /// Explicit and environment paths are returned even if they do not exist so that the error
+    /// names the file the user asked for.
+    pub fn locate(explicit: Option<&Path>) -> Result<PathBuf, UnitError> {
+        if let Some(path) = explicit {
+            return Ok(path.to_path_buf());
         }
-        let name = row
-            .unwrap()
-            .get_value(0)
-            .unwrap()
-            .as_text()
-            .unwrap()
-            .to_owned();
+        if let Some(path) = std::env::var_os(Self::DB_ENV).filter(|p| !p.is_empty()) {
+            return Ok(PathBuf::from(path));
+        }
+
+        let data_home = data_home();
+        let data_dirs = std::env::var("XDG_DATA_DIRS")
+            .ok()
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| "/usr/local/share:/usr/share".to_owned());
+        let mut candidates: Vec<PathBuf> = data_home
+            .into_iter()
+            .chain(data_dirs.split(':').map(PathBuf::from))
+            .map(|dir| dir.join(Self::DB_FILE))
+            .collect();
+        candidates.extend(option_env!("DB_PATH").map(PathBuf::from));
+
+        candidates
+            .iter()
+            .find(|path| path.is_file())
+            .cloned()
+            .ok_or_else(|| {
+                UnitError::MissingDatabase(
+                    candidates
+                        .iter()
+                        .map(|p| p.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )
+            })
+    }
 
-        if rows.next().await.unwrap().is_some() {
+    /// Modification time and size of the database and its write-ahead log.
+    fn impl_revision(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let wal = PathBuf::from(format!("{}-wal", self.path.display()));
+        for path in [&self.path, &wal] {
+            if let Ok(meta) = std::fs::metadata(path) {
+                meta.modified().ok().hash(&mut hasher);
+                meta.len().hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Runs a prepared statement bound to `params`, a statement is shared so it is reset first.
+    async fn query(
+        statement: &turso::Statement,
+        params: impl turso::IntoParams,
+    ) -> Result<turso::Rows, UnitError> {
+        let mut statement = statement.clone();
+        statement.reset();
+        statement.query(params).await.map_err(sql_error)
+    }
+
+    /// Runs a prepared statement expecting at most one row.
+    async fn query_one(
+        &self,
+        statement: &turso::Statement,
+        key: &str,
+    ) -> Result<Option<turso::Row>, UnitError> {
+        let _guard = self.lock.lock().await;
+        let mut rows = Self::query(statement, [key]).await?;
+        let row = rows.next().await.map_err(sql_error)?;
+        if row.is_some() && rows.next().await.map_err(sql_error)?.is_some() {
             return Err(UnitError::Query(
                 "SqlQuery: should have exactly one result".to_owned(),
             ));
         }
+        Ok(row)
+    }
 
-        Ok(name)
+    /// Every unit name, possibly none.
+    pub(super) async fn unit_names(&self) -> Result<Vec<String>, UnitError> {
+        let _guard = self.lock.lock().await;
+        let mut rows = Self::query(&self.statements.unit_list, ()).await?;
+        let mut names = Vec::new();
+        while let Some(row) = rows.next().await.map_err(sql_error)? {
+            let name = row.get_value(0).map_err(sql_error)?;
+            names.push(name.as_text().unwrap().to_owned());
+        }
+        Ok(names)
     }
 
-    async fn impl_get_dim_from_unit(
+    /// Every dimension with its name, in table order.
+    pub(super) async fn dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        let _guard = self.lock.lock().await;
+        let mut rows = Self::query(&self.statements.dimensions, ()).await?;
+        let mut dimensions = Vec::new();
+        while let Some(row) = rows.next().await.map_err(sql_error)? {
+            dimensions.push(dimension_from_row(&row)?);
+        }
+        Ok(dimensions)
+    }
+
+    /// Whether `unit_name` is a row of the catalogue or an alias of one, prefixes aside.
+    pub(super) async fn has_unit(&self, unit_name: &str) -> Result<bool, UnitError> {
+        Ok(self.impl_row_name(unit_name).await?.is_some())
+    }
+
+    /// Every alias with the unit it names, in table order.
+    pub(super) async fn aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        let _guard = self.lock.lock().await;
+        let mut rows = Self::query(&self.statements.aliases, ()).await?;
+        let mut aliases = Vec::new();
+        while let Some(row) = rows.next().await.map_err(sql_error)? {
+            let text = |i| {
+                row.get_value(i)
+                    .ok()
+                    .and_then(|v| v.as_text().cloned())
+                    .unwrap_or_default()
+            };
+            aliases.push((text(0), text(1)));
+        }
+        Ok(aliases)
+    }
+
+    pub(super) async fn find_dimension(
         &self,
         dim_name: &str,
-    ) -> Result<(String, Dimension), UnitError> {
-        //Not implemented yet in Turso, use 2 queries instead
-        // let query = format!(
-        //     "SELECT dimension_name, mass, length, duration, current, amount, temperature, luminosity
-        //     FROM dimension
-        //     WHERE dimension_name IN ({});",
-        //     Self::get_query_dimension(unit_name)
-        // );
+    ) -> Result<Option<Dimension>, UnitError> {
+        match self.query_one(&self.statements.dimension, dim_name).await? {
+            Some(row) => Ok(Some(dimension_from_row(&row)?.1)),
+            None => Ok(None),
+        }
+    }
+
+    /// Runs a write statement, serialised with the queries.
+    pub(super) async fn execute(
+        &self,
+        sql: &str,
+        params: impl turso::IntoParams,
+    ) -> Result<u64, UnitError> {
+        let _guard = self.lock.lock().await;
+        self.conn.execute(sql, params).await.map_err(sql_error)
+    }
 
+    /// Rows of both tables as stored, for [`SqlUnitQuery::check`].
+    pub(super) async fn rows(&self) -> Result<(Vec<UnitRow>, Vec<DimensionRow>), UnitError> {
+        let _guard = self.lock.lock().await;
+        let mut units = Vec::new();
         let query = format!(
-            "SELECT dimension_name, mass,  duration,length, current, amount, temperature, luminosity
-            FROM dimension
-            WHERE dimension_name ='{}'",dim_name
-            // Self::get_query_dimension(unit_name)
+            "SELECT unit_name, dimension_name, conversionfactor, conversionoffset, definition
+            FROM {} ORDER BY id",
+            Self::TABLE_NAME
         );
-        let mut rows = self.conn.query(&query, ()).await.unwrap();
-        let row = rows.next().await.unwrap().unwrap();
-        assert!(row.column_count() == 8);
-        if rows.next().await.unwrap().is_some() {
-            return Err(UnitError::Query(
-                "SqlQuery: should have exactly one result".to_owned(),
-            ));
+        let mut rows = self.conn.query(&query, ()).await.map_err(sql_error)?;
+        while let Some(row) = rows.next().await.map_err(sql_error)? {
+            let text = |i| row.get_value(i).ok().and_then(|v| v.as_text().cloned());
+            let number = |i| match row.get_value(i) {
+                Ok(turso::Value::Null) | Err(_) => None,
+                Ok(value) => as_number(value),
+            };
+            units.push(UnitRow {
+                name: text(0).unwrap_or_default(),
+                dimension: text(1),
+                factor: number(2),
+                offset: number(3).unwrap_or(0.),
+                definition: text(4),
+            });
         }
 
-        let dimension_name = row.get_value(0).unwrap().as_text().unwrap().to_owned();
-        let mut dimension = Dimension::default();
-        for i in 1..dimension.0.len() {
-            dimension.0[i - 1] = row.get_value(i).unwrap().as_real().unwrap().to_owned() as i32;
+        let mut dimensions = Vec::new();
+        let query = format!(
+            "SELECT {} FROM dimension ORDER BY iddimension",
+            DIMENSION_COLUMNS
+        );
+        let mut rows = self.conn.query(&query, ()).await.map_err(sql_error)?;
+        while let Some(row) = rows.next().await.map_err(sql_error)? {
+            let name = row.get_value(0).ok().and_then(|v| v.as_text().cloned());
+            dimensions.push(DimensionRow {
+                name: name.unwrap_or_default(),
+                dimension: dimension_from_row(&row)
+                    .map(|(_, dimension)| dimension)
+                    .map_err(|e| e.to_string()),
+            });
         }
+        Ok((units, dimensions))
+    }
 
-        Ok((dimension_name, dimension))
+    /// Checks the whole catalogue: dimensions referenced by the units exist, names are unique,
+    /// factors are positive and stored factors and dimensions agree with the definitions. Every
+    /// problem is reported, none stops the check.
+    pub async fn check(&self) -> Result<Vec<Diagnostic>, UnitError> {
+        let (units, dimensions) = self.rows().await?;
+        let aliases = self.aliases().await?;
+        Ok(check_catalogue(self, &units, &dimensions, &aliases).await)
     }
 
-    async fn impl_conversion_factor(&self, unit_name: &str) -> Result<f64, UnitError> {
-        let query = format!(
-            "SELECT conversionfactor  FROM conversiontable WHERE unit_name = '{}'",
-            unit_name
-        );
+    async fn impl_query_unit_list(&self) -> Result<Vec<String>, UnitError> {
+        let names = self.unit_names().await?;
+        if names.is_empty() {
+            Err(UnitError::Custom("Empty databse".to_owned()))
+        } else {
+            Ok(names)
+        }
+    }
 
-        let mut rows = self.conn.query(&query, ()).await.unwrap();
-        let row = rows.next().await.unwrap();
-        if row.is_none() {
-            return Err(UnitError::Query(
-                "SqlQuery: should have exactly one result".to_owned(),
-            ));
+    /// Runs a text column statement on `unit_name`, `Some(None)` when the row holds `NULL`.
+    async fn impl_text(
+        &self,
+        statement: &turso::Statement,
+        unit_name: &str,
+    ) -> Result<Option<Option<String>>, UnitError> {
+        let Some(row) = self.query_one(statement, unit_name).await? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            row.get_value(0).map_err(sql_error)?.as_text().cloned(),
+        ))
+    }
+
+    /// Row of the catalogue named `unit_name`, directly or through an alias, prefixes aside.
+    async fn impl_row_name(&self, unit_name: &str) -> Result<Option<String>, UnitError> {
+        if self
+            .impl_text(&self.statements.dimension_name, unit_name)
+            .await?
+            .is_some()
+        {
+            return Ok(Some(unit_name.to_owned()));
         }
-        let row = row.unwrap();
+        Ok(self
+            .impl_text(&self.statements.alias, unit_name)
+            .await?
+            .flatten())
+    }
+
+    /// Row of the catalogue behind `unit_name` with the prefix applied to it: the name itself,
+    /// its alias, then its prefix decomposition (whose base may be an alias too).
+    async fn impl_resolve(
+        &self,
+        unit_name: &str,
+    ) -> Result<(Option<&'static Prefix>, String), UnitError> {
+        if let Some(name) = self.impl_row_name(unit_name).await? {
+            return Ok((None, name));
+        }
+        let (prefix, base) = AsyncUnitQuery::resolve_prefix(self, unit_name).await?;
+        match self.impl_row_name(&base).await? {
+            Some(name) => Ok((Some(prefix), name)),
+            None => Err(UnitError::bad_unit(unit_name)),
+        }
+    }
+
+    async fn impl_get_dim_from_unit(
+        &self,
+        dim_name: &str,
+    ) -> Result<(String, Dimension), UnitError> {
+        let dimension =
+            self.find_dimension(dim_name)
+                .await?
+                .ok_or_else(|| UnitError::NoDimension {
+                    dimension: dim_name.to_owned(),
+                })?;
+        Ok((dim_name.to_owned(), dimension))
+    }
+
+    async fn impl_number(
+        &self,
+        statement: &turso::Statement,
+        unit_name: &str,
+    ) -> Result<Option<f64>, UnitError> {
+        let Some(row) = self.query_one(statement, unit_name).await? else {
+            return Ok(None);
+        };
         assert!(row.column_count() == 1);
-        if rows.next().await.unwrap().is_some() {
-            return Err(UnitError::Query(
-                "SqlQuery: should have exactly one result".to_owned(),
-            ));
+
+        match as_number(row.get_value(0).map_err(sql_error)?) {
+            Some(v) => Ok(Some(v)),
+            None => Err(UnitError::Query(format!(
+                "SqlQuery: value of {} is not a number",
+                unit_name
+            ))),
         }
+    }
 
-        Ok(row.get_value(0).unwrap().as_real().unwrap().to_owned())
+    async fn impl_accepts_prefix(
+        &self,
+        unit_name: &str,
+        kind: PrefixKind,
+    ) -> Result<bool, UnitError> {
+        let statement = match kind {
+            PrefixKind::Si => &self.statements.si_prefix,
+            PrefixKind::Binary => &self.statements.binary_prefix,
+        };
+        let Some(name) = self.impl_row_name(unit_name).await? else {
+            return Ok(false);
+        };
+        Ok(self
+            .impl_number(statement, &name)
+            .await?
+            .is_some_and(|flag| flag != 0.))
     }
 
-    async fn check_db_integrity(&self) -> bool {
+    async fn check_db_integrity(conn: &turso::Connection) -> Result<(), String> {
         let query = "SELECT *  FROM dimension";
-        let mut rows = self.conn.query(query, ()).await.unwrap();
-        let row = rows.next().await.unwrap();
-        if row.is_none() {
-            return false;
+        let mut rows = conn.query(query, ()).await.map_err(|e| e.to_string())?;
+        match rows.next().await.map_err(|e| e.to_string())? {
+            None => Err("empty dimension table".to_owned()),
+            Some(row) if row.column_count() != 9 => Err(format!(
+                "dimension table has {} columns instead of 9",
+                row.column_count()
+            )),
+            Some(_) => Ok(()),
         }
-        let row = row.unwrap();
-        row.column_count() == 9
     }
 }
 
-impl UnitQuery for SqlUnitQuery {
-    fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
-        block_on(self.impl_conversion_factor(&unit.name))
+impl SqlUnitQuery {
+    /// Runs a numeric column statement on `unit_name`, scaled by its prefix when `scale` is set.
+    async fn impl_lookup_number(
+        &self,
+        statement: &turso::Statement,
+        unit_name: &str,
+        scale: bool,
+    ) -> Result<f64, UnitError> {
+        let (prefix, name) = self.impl_resolve(unit_name).await?;
+        match (self.impl_number(statement, &name).await?, prefix) {
+            (Some(value), Some(prefix)) if scale => Ok(prefix.factor * value),
+            (Some(value), _) => Ok(value),
+            (None, _) => Err(UnitError::bad_unit(unit_name)),
+        }
     }
+}
 
-    fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
-        block_on(self.impl_query_unit_list())
+/// Catalogue built from the embedded migrations in the temp directory, so that tests do not
+/// depend on an installed database.
+#[cfg(all(test, feature = "embedded"))]
+impl SqlUnitQuery {
+    pub(crate) async fn temporary(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("runit-base-{}-{}.db", name, std::process::id()));
+        for stale in [path.clone(), path.with_extension("db-wal")] {
+            let _ = std::fs::remove_file(stale);
+        }
+        Self::connect(&path, Some(&crate::migrations::embedded()))
+            .await
+            .unwrap()
     }
+}
 
-    fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+impl AsyncUnitQuery for SqlUnitQuery {
+    async fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        self.impl_lookup_number(&self.statements.conversion_factor, &unit.name, true)
+            .await
+    }
+
+    async fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        self.impl_lookup_number(&self.statements.conversion_offset, &unit.name, false)
+            .await
+    }
+
+    async fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+        self.impl_query_unit_list().await
+    }
+
+    async fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
         let dim_name = match &unit.dim {
             Some(name) => name.to_owned(),
-            None => block_on(self.impl_get_dim_name(&unit.name))?,
+            None => AsyncUnitQuery::get_dimension_name(self, unit).await?,
         };
 
-        block_on(self.impl_get_dim_from_unit(&dim_name))
+        self.impl_get_dim_from_unit(&dim_name).await
+    }
+
+    async fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
+        let (_, row_name) = self.impl_resolve(&p_unit.name).await?;
+        let name = self
+            .impl_text(&self.statements.dimension_name, &row_name)
+            .await?
+            .ok_or_else(|| UnitError::bad_unit(&p_unit.name))?;
+        name.ok_or_else(|| UnitError::NoStoredDimension {
+            unit: p_unit.name.clone(),
+        })
+    }
+
+    async fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError> {
+        self.impl_accepts_prefix(unit_name, kind).await
+    }
+
+    async fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+        match self.impl_resolve(&unit.name).await? {
+            (None, name) => Ok(self
+                .impl_text(&self.statements.definition, &name)
+                .await?
+                .flatten()),
+            (Some(prefix), base) => Ok(Some(prefixed_definition(prefix, &base))),
+        }
+    }
+
+    async fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        self.dimensions().await
+    }
+
+    async fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        self.aliases().await
+    }
+
+    fn revision(&self) -> u64 {
+        self.impl_revision()
+    }
+}
+
+/// Blocking facade over [`AsyncUnitQuery`], must not be called from inside an async runtime.
+impl UnitQuery for SqlUnitQuery {
+    fn get_conversion_factor(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        block_on(AsyncUnitQuery::get_conversion_factor(self, unit))
+    }
+
+    fn get_conversion_offset(&self, unit: &ElementUnit) -> Result<f64, UnitError> {
+        block_on(AsyncUnitQuery::get_conversion_offset(self, unit))
+    }
+
+    fn get_unit_list(&self) -> Result<Vec<String>, UnitError> {
+        block_on(AsyncUnitQuery::get_unit_list(self))
+    }
+
+    fn get_dimension(&self, unit: &ElementUnit) -> Result<(String, Dimension), UnitError> {
+        block_on(AsyncUnitQuery::get_dimension(self, unit))
     }
 
     fn get_dimension_name(&self, p_unit: &ElementUnit) -> Result<String, UnitError> {
-        block_on(self.impl_get_dim_name(&p_unit.name))
+        block_on(AsyncUnitQuery::get_dimension_name(self, p_unit))
+    }
+
+    fn accepts_prefix(&self, unit_name: &str, kind: PrefixKind) -> Result<bool, UnitError> {
+        block_on(AsyncUnitQuery::accepts_prefix(self, unit_name, kind))
+    }
+
+    fn resolve_prefix(&self, unit_name: &str) -> Result<(&'static Prefix, String), UnitError> {
+        block_on(AsyncUnitQuery::resolve_prefix(self, unit_name))
+    }
+
+    fn get_definition(&self, unit: &ElementUnit) -> Result<Option<String>, UnitError> {
+        block_on(AsyncUnitQuery::get_definition(self, unit))
+    }
+
+    fn get_aliases(&self) -> Result<Vec<(String, String)>, UnitError> {
+        block_on(AsyncUnitQuery::get_aliases(self))
+    }
+
+    fn get_dimensions(&self) -> Result<Vec<(String, Dimension)>, UnitError> {
+        block_on(AsyncUnitQuery::get_dimensions(self))
+    }
+
+    fn revision(&self) -> u64 {
+        self.impl_revision()
     }
 }
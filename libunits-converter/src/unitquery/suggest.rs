@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! "Did you mean" engine, ranking catalogue names close to an unknown unit.
+
+use std::collections::HashMap;
+
+/// Most names suggested for one unknown unit.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// `name` with a plural ending removed, `None` when it has none (`kgs`, `bars`, `inches`).
+fn singular(name: &str) -> Option<&str> {
+    if name.chars().count() < 3 {
+        return None;
+    }
+    name.strip_suffix("es")
+        .filter(|stem| stem.ends_with(['s', 'x', 'h']))
+        .or_else(|| name.strip_suffix('s'))
+}
+
+/// Spellings of `name` worth looking up as they are, prefixed units included: its lower case
+/// and singular forms. `name` itself is not part of them.
+pub(crate) fn variants(name: &str) -> Vec<String> {
+    let lower = name.to_lowercase();
+    let mut variants = Vec::new();
+    for variant in [Some(lower.as_str()), singular(name), singular(&lower)]
+        .into_iter()
+        .flatten()
+    {
+        if variant != name && !variants.iter().any(|v| v == variant) {
+            variants.push(variant.to_owned());
+        }
+    }
+    variants
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions and swaps of two
+/// neighbours cost one.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Three rows of the table: two rows back, previous and current
+    let mut rows = vec![vec![0; b.len() + 1]; 3];
+    rows[1] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        rows[2][0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (rows[1][j] + 1)
+                .min(rows[2][j - 1] + 1)
+                .min(rows[1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(rows[0][j - 2] + 1);
+            }
+            rows[2][j] = d;
+        }
+        rows.rotate_left(1);
+    }
+    rows[1][b.len()]
+}
+
+/// Edits allowed between a name and its suggestions, none for names of one or two characters
+/// where a single edit reaches most of the catalogue.
+fn max_distance(name: &str) -> usize {
+    match name.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/// Score of `candidate` as a suggestion for `name`, lower is closer. Spelling differences count
+/// twice as much as case ones.
+fn score(name: &str, candidate: &str) -> Option<usize> {
+    let (name_lower, candidate_lower) = (name.to_lowercase(), candidate.to_lowercase());
+    let edits = [Some(name_lower.as_str()), singular(&name_lower)]
+        .into_iter()
+        .flatten()
+        .map(|n| distance(n, &candidate_lower))
+        .min()?;
+    if edits > max_distance(name) {
+        return None;
+    }
+    Some(2 * edits + usize::from(name_lower == candidate_lower && name != candidate))
+}
+
+/// Up to [`MAX_SUGGESTIONS`] catalogue units for the unknown `name`, the closest ones first and
+/// only those.
+///
+/// `units` are the catalogue names, `aliases` the alternative names with their unit, and `known`
+/// the [`variants`] of `name` found to be valid units, prefixed ones included.
+pub(crate) fn rank(
+    name: &str,
+    units: &[String],
+    aliases: &[(String, String)],
+    known: &[String],
+) -> Vec<String> {
+    // Best score of each unit, over its name, aliases and the variants naming it
+    let mut best: HashMap<&str, usize> = HashMap::new();
+    let candidates = known
+        .iter()
+        .map(|k| (k.as_str(), k.as_str()))
+        .chain(units.iter().map(|u| (u.as_str(), u.as_str())))
+        .chain(aliases.iter().map(|(a, u)| (a.as_str(), u.as_str())));
+    for (spelling, unit) in candidates {
+        if unit == name {
+            continue;
+        }
+        if let Some(score) = score(name, spelling) {
+            let entry = best.entry(unit).or_insert(score);
+            *entry = (*entry).min(score);
+        }
+    }
+    let mut ranked: Vec<(&str, usize)> = best.into_iter().collect();
+    ranked.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(b.0)));
+    // A spelling fix further than the closest match is noise (`g` once `kg` matches `kgs`)
+    let cutoff = ranked.first().map_or(0, |(_, score)| score + 1);
+    ranked
+        .into_iter()
+        .take_while(|(_, score)| *score <= cutoff)
+        .take(MAX_SUGGESTIONS)
+        .map(|(unit, _)| unit.to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_distance() {
+        assert!(distance("kg", "kg") == 0);
+        assert!(distance("kgs", "kg") == 1);
+        assert!(distance("mni", "min") == 1);
+        assert!(distance("", "bar") == 3);
+        assert!(distance("metre", "meter") == 1);
+        assert!(distance("metre", "mile") == 3);
+    }
+
+    #[test]
+    fn test_variants() {
+        assert!(variants("Km") == names(&["km"]));
+        assert!(variants("kms") == names(&["km"]));
+        assert!(variants("Inches") == names(&["inches", "Inch", "inch"]));
+        assert!(variants("kg").is_empty());
+    }
+
+    #[test]
+    fn test_rank() {
+        let units = names(&["kg", "g", "bar", "m", "min", "h", "hp", "Pa"]);
+        let aliases = vec![("gram".to_owned(), "g".to_owned())];
+        assert!(rank("Kg", &units, &aliases, &[]) == names(&["kg"]));
+        assert!(rank("kgs", &units, &aliases, &[]) == names(&["kg"]));
+        assert!(rank("bars", &units, &aliases, &[]) == names(&["bar"]));
+        assert!(rank("grams", &units, &aliases, &[]) == names(&["g"]));
+        assert!(rank("mni", &units, &aliases, &[]) == names(&["min"]));
+        assert!(rank("pa", &units, &aliases, &[]) == names(&["Pa"]));
+        assert!(rank("Km", &units, &aliases, &names(&["km"])) == names(&["km"]));
+        // Short names only get case and plural fixes
+        assert!(rank("x", &units, &aliases, &[]).is_empty());
+        assert!(rank("furlong", &units, &aliases, &[]).is_empty());
+    }
+}
@@ -0,0 +1,6 @@
+BEGIN TRANSACTION;
+ALTER TABLE conversiontable ADD COLUMN conversionoffset double DEFAULT 0.0;
+UPDATE conversiontable SET unit_name = 'degC', conversionfactor = 1.0, conversionoffset = 273.15 WHERE unit_name = 'deg';
+INSERT INTO conversiontable VALUES(34,'degF','temperature',0.55555555555555555556,255.37222222222222222222);
+INSERT INTO conversiontable VALUES(35,'degR','temperature',0.55555555555555555556,0.0);
+COMMIT;
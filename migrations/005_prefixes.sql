@@ -0,0 +1,8 @@
+BEGIN TRANSACTION;
+ALTER TABLE conversiontable ADD COLUMN si_prefix integer DEFAULT 0;
+ALTER TABLE conversiontable ADD COLUMN binary_prefix integer DEFAULT 0;
+UPDATE conversiontable SET si_prefix = 1 WHERE unit_name IN ('g', 'm', 's', 'Pa', 'bar', 'mol', 'v', 'A', 'K', 'ohm', 'N', 'J', 'cal', 'L');
+INSERT INTO dimension VALUES(12,0.0,0.0,0.0,0.0,0.0,0.0,0.0,'information');
+INSERT INTO conversiontable VALUES(36,'bit','information',1.0,0.0,1,1);
+INSERT INTO conversiontable VALUES(37,'B','information',8.0,0.0,1,1);
+COMMIT;
@@ -0,0 +1,6 @@
+BEGIN TRANSACTION;
+INSERT INTO conversiontable VALUES(38,'ft','length',0.3048,0.0,0,0);
+INSERT INTO conversiontable VALUES(39,'yd','length',0.9144,0.0,0,0);
+INSERT INTO conversiontable VALUES(40,'ft''','length',0.3048,0.0,0,0);
+INSERT INTO conversiontable VALUES(41,'in"','length',0.0254,0.0,0,0);
+COMMIT;
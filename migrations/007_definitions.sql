@@ -0,0 +1,18 @@
+BEGIN TRANSACTION;
+ALTER TABLE conversiontable ADD COLUMN definition text DEFAULT NULL;
+UPDATE dimension SET duration = -2.0, length = 1.0 WHERE dimension_name = 'force';
+UPDATE conversiontable SET definition = 'kg*m/s^2' WHERE unit_name = 'N';
+UPDATE conversiontable SET definition = 'N*m' WHERE unit_name = 'J';
+UPDATE conversiontable SET definition = 'N/m^2' WHERE unit_name = 'Pa';
+UPDATE conversiontable SET definition = '100000*Pa' WHERE unit_name = 'bar';
+UPDATE conversiontable SET definition = 'W/A' WHERE unit_name = 'v';
+UPDATE conversiontable SET definition = 'v/A' WHERE unit_name = 'ohm';
+UPDATE conversiontable SET definition = '4.184*J' WHERE unit_name = 'cal';
+UPDATE conversiontable SET definition = '1000*cal' WHERE unit_name = 'kcal';
+UPDATE conversiontable SET definition = 'dm^3' WHERE unit_name = 'L';
+UPDATE conversiontable SET definition = 'm^3' WHERE unit_name = 'm3';
+UPDATE conversiontable SET definition = '60*s' WHERE unit_name = 'min';
+UPDATE conversiontable SET definition = '60*min' WHERE unit_name = 'h';
+INSERT INTO conversiontable VALUES(42,'W',NULL,NULL,0.0,1,0,'J/s');
+INSERT INTO conversiontable VALUES(43,'kWh',NULL,NULL,0.0,0,0,'1000*W*h');
+COMMIT;
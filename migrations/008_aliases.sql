@@ -0,0 +1,75 @@
+BEGIN TRANSACTION;
+CREATE TABLE alias (
+  id integer PRIMARY KEY
+, alias_name varchar(45) NOT NULL
+, unit_name varchar(45) NOT NULL
+);
+INSERT INTO alias VALUES(0,'gram','g');
+INSERT INTO alias VALUES(1,'grams','g');
+INSERT INTO alias VALUES(2,'gramme','g');
+INSERT INTO alias VALUES(3,'grammes','g');
+INSERT INTO alias VALUES(4,'tonne','t');
+INSERT INTO alias VALUES(5,'tonnes','t');
+INSERT INTO alias VALUES(6,'metre','m');
+INSERT INTO alias VALUES(7,'meter','m');
+INSERT INTO alias VALUES(8,'metres','m');
+INSERT INTO alias VALUES(9,'meters','m');
+INSERT INTO alias VALUES(10,'µm','microm');
+INSERT INTO alias VALUES(11,'μm','microm');
+INSERT INTO alias VALUES(12,'um','microm');
+INSERT INTO alias VALUES(13,'micron','microm');
+INSERT INTO alias VALUES(14,'microns','microm');
+INSERT INTO alias VALUES(15,'second','s');
+INSERT INTO alias VALUES(16,'seconds','s');
+INSERT INTO alias VALUES(17,'sec','s');
+INSERT INTO alias VALUES(18,'minute','min');
+INSERT INTO alias VALUES(19,'minutes','min');
+INSERT INTO alias VALUES(20,'hour','h');
+INSERT INTO alias VALUES(21,'hours','h');
+INSERT INTO alias VALUES(22,'hr','h');
+INSERT INTO alias VALUES(23,'pascal','Pa');
+INSERT INTO alias VALUES(24,'pascals','Pa');
+INSERT INTO alias VALUES(25,'bars','bar');
+INSERT INTO alias VALUES(26,'inch','in');
+INSERT INTO alias VALUES(27,'inches','in');
+INSERT INTO alias VALUES(28,'mile','mi');
+INSERT INTO alias VALUES(29,'miles','mi');
+INSERT INTO alias VALUES(30,'mole','mol');
+INSERT INTO alias VALUES(31,'moles','mol');
+INSERT INTO alias VALUES(32,'V','v');
+INSERT INTO alias VALUES(33,'volt','v');
+INSERT INTO alias VALUES(34,'volts','v');
+INSERT INTO alias VALUES(35,'ampere','A');
+INSERT INTO alias VALUES(36,'amperes','A');
+INSERT INTO alias VALUES(37,'amp','A');
+INSERT INTO alias VALUES(38,'amps','A');
+INSERT INTO alias VALUES(39,'kelvin','K');
+INSERT INTO alias VALUES(40,'kelvins','K');
+INSERT INTO alias VALUES(41,'ohms','ohm');
+INSERT INTO alias VALUES(42,'newton','N');
+INSERT INTO alias VALUES(43,'newtons','N');
+INSERT INTO alias VALUES(44,'joule','J');
+INSERT INTO alias VALUES(45,'joules','J');
+INSERT INTO alias VALUES(46,'calorie','cal');
+INSERT INTO alias VALUES(47,'calories','cal');
+INSERT INTO alias VALUES(48,'lb','lbs');
+INSERT INTO alias VALUES(49,'pound','lbs');
+INSERT INTO alias VALUES(50,'pounds','lbs');
+INSERT INTO alias VALUES(51,'l','L');
+INSERT INTO alias VALUES(52,'litre','L');
+INSERT INTO alias VALUES(53,'liter','L');
+INSERT INTO alias VALUES(54,'litres','L');
+INSERT INTO alias VALUES(55,'liters','L');
+INSERT INTO alias VALUES(56,'celsius','degC');
+INSERT INTO alias VALUES(57,'fahrenheit','degF');
+INSERT INTO alias VALUES(58,'rankine','degR');
+INSERT INTO alias VALUES(59,'bits','bit');
+INSERT INTO alias VALUES(60,'byte','B');
+INSERT INTO alias VALUES(61,'bytes','B');
+INSERT INTO alias VALUES(62,'foot','ft');
+INSERT INTO alias VALUES(63,'feet','ft');
+INSERT INTO alias VALUES(64,'yard','yd');
+INSERT INTO alias VALUES(65,'yards','yd');
+INSERT INTO alias VALUES(66,'watt','W');
+INSERT INTO alias VALUES(67,'watts','W');
+COMMIT;
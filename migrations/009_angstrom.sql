@@ -0,0 +1,4 @@
+BEGIN TRANSACTION;
+INSERT INTO conversiontable VALUES(44,'angstrom','length',1e-10,0.0,0,0,'1e-10*m');
+INSERT INTO alias VALUES(68,'angstroms','angstrom');
+COMMIT;
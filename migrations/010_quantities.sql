@@ -0,0 +1,6 @@
+BEGIN TRANSACTION;
+INSERT INTO dimension VALUES(13,1.0,-2.0,2.0,0.0,0.0,0.0,0.0,'torque');
+INSERT INTO dimension VALUES(14,1.0,-3.0,2.0,0.0,0.0,0.0,0.0,'power');
+INSERT INTO dimension VALUES(15,0.0,-1.0,0.0,0.0,0.0,0.0,0.0,'frequency');
+INSERT INTO dimension VALUES(16,0.0,0.0,2.0,0.0,0.0,0.0,0.0,'area');
+COMMIT;